@@ -0,0 +1,23 @@
+use futures_util::StreamExt;
+use victron_energy_api::mqtt::{MqttClient, MqttOptions, ValueKey};
+
+#[tokio::main]
+async fn main() {
+    // The plaintext MQTT broker has to be enabled on the GX device, under Settings > Services.
+    const GX_ADDRESS: &str = "192.168.1.50";
+
+    let mqtt = MqttClient::connect_local(GX_ADDRESS, MqttOptions::local())
+        .await
+        .expect("Connected to the GX device");
+
+    println!("Portal id: {}", mqtt.portal_id());
+
+    let battery_soc = ValueKey::new("system", 0, "/Dc/Battery/Soc");
+    let mut updates = Box::pin(mqtt.updates());
+
+    while let Some(update) = updates.next().await {
+        if update.key == battery_soc {
+            println!("Battery state of charge: {:?}", update.value());
+        }
+    }
+}
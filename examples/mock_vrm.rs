@@ -0,0 +1,47 @@
+use futures_util::StreamExt;
+use victron_energy_api::mock::{MockFailure, MockVrm};
+
+#[tokio::main]
+async fn main() {
+    // The mock serves fixtures for every supported endpoint, without touching the network.
+    let vrm = MockVrm::new();
+
+    let victron = vrm.login().await.expect("Logged in successfully");
+
+    let installations = victron
+        .get_all_installations_or_sites(true)
+        .await
+        .expect("Got installations successfully");
+
+    for installation in &installations {
+        println!("{}: {}", installation.site_id, installation.name);
+    }
+
+    // Fleet requests run a few at a time, and report errors per installation.
+    let mut alarms = victron.fleet(&installations).alarms();
+
+    while let Some(site) = alarms.next().await {
+        match site.result {
+            Ok(alarms) => println!(
+                "{}: {} alarms configured",
+                site.site_id,
+                alarms.alarms.len()
+            ),
+            Err(e) => println!("{}: {e}", site.site_id),
+        }
+    }
+
+    // Scripted failures are served once, in the order they were added.
+    vrm.fail_next_on("/users/me", MockFailure::RateLimited);
+
+    let error = victron
+        .get_user_info()
+        .await
+        .expect_err("The request was rate limited");
+
+    println!("Error: {error}");
+
+    for request in vrm.requests() {
+        println!("{} {} {:?}", request.method, request.path, request.query);
+    }
+}
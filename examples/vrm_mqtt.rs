@@ -0,0 +1,31 @@
+use futures_util::StreamExt;
+use victron_energy_api::{mqtt::MqttOptions, Victron};
+
+#[tokio::main]
+async fn main() {
+    const USERNAME: &str = "john@example.com";
+    const ACCESS_TOKEN: &str = "sometoken";
+
+    let victron = Victron::login_access_token(USERNAME, ACCESS_TOKEN)
+        .await
+        .expect("Logged in successfully");
+
+    let installations = victron
+        .get_all_installations_or_sites(false)
+        .await
+        .expect("Got installations successfully");
+
+    let installation = installations.first().expect("Found an installation");
+
+    // To try this against a local broker instead, use `MqttOptions::new().host("localhost").port(1883).plaintext()`.
+    let mqtt = victron
+        .connect_mqtt(installation, MqttOptions::new())
+        .await
+        .expect("Connected to the MQTT broker");
+
+    let mut updates = Box::pin(mqtt.updates());
+
+    while let Some(update) = updates.next().await {
+        println!("{}: {:?}", update.key, update.value());
+    }
+}
@@ -0,0 +1,39 @@
+//! Lists the installations of the VRM demo account from the browser.
+//!
+//! Build it for the web with:
+//!
+//! ```sh
+//! cargo build --example wasm --target wasm32-unknown-unknown
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/debug/examples/wasm.wasm
+//! ```
+//!
+//! and load `pkg/wasm.js` from a page. The installations are logged to the browser console.
+
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(async {
+        let message = match list_installations().await {
+            Ok(names) => format!("Demo installations: {}", names.join(", ")),
+            Err(e) => format!("Could not list the demo installations: {e}"),
+        };
+
+        web_sys::console::log_1(&wasm_bindgen::JsValue::from_str(&message));
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn list_installations() -> Result<Vec<String>, victron_energy_api::Error> {
+    let victron = victron_energy_api::Victron::login_as_demo().await?;
+
+    Ok(victron
+        .get_all_installations_or_sites(false)
+        .await?
+        .into_iter()
+        .map(|installation| installation.name)
+        .collect())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    eprintln!("This example runs in the browser, build it with `--target wasm32-unknown-unknown`.");
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoint::Endpoint,
+    ids::{DataAttributeId, SiteId},
+    transport::HttpTransport,
+    Error, Method, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
+    /// Retrieves the alarms configured for an installation, and the data attributes they watch.
+    ///
+    /// Alarms that are currently active are listed in [`crate::installations::Installation::current_alarms`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub async fn get_alarms(&self, site_id: SiteId) -> Result<Alarms, Error> {
+        let success = self.execute(&AlarmsEndpoint { site_id }).await?;
+
+        Ok(Alarms {
+            alarms: success.alarms,
+            attributes: success.attributes,
+        })
+    }
+}
+
+struct AlarmsEndpoint {
+    site_id: SiteId,
+}
+
+impl Endpoint for AlarmsEndpoint {
+    type Response = AlarmsSuccess;
+
+    const METHOD: Method = Method::GET;
+
+    fn path(&self) -> String {
+        format!("/installations/{}/alarms", self.site_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlarmsSuccess {
+    pub success: bool,
+    pub alarms: Vec<Alarm>,
+    #[serde(default)]
+    pub attributes: Vec<AlarmAttribute>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// The alarms of an installation, as returned by [`Victron::get_alarms`].
+pub struct Alarms {
+    pub alarms: Vec<Alarm>,
+    /// The data attributes the alarms watch.
+    pub attributes: Vec<AlarmAttribute>,
+}
+
+impl Alarms {
+    #[must_use]
+    /// Returns the data attribute an alarm watches.
+    pub fn attribute(&self, alarm: &Alarm) -> Option<&AlarmAttribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.data_id == alarm.data_id)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// An alarm that is raised when a data attribute leaves a range.
+pub struct Alarm {
+    #[serde(rename = "idDataAttribute")]
+    pub data_id: DataAttributeId,
+    pub instance: i32,
+    #[serde(rename = "lowAlarm")]
+    /// The alarm is raised when the value drops below this.
+    pub low_alarm: Option<f64>,
+    #[serde(rename = "lowAlarmHysteresis")]
+    /// How far the value has to rise above [`Alarm::low_alarm`] again before the alarm is cleared.
+    pub low_alarm_hysteresis: Option<f64>,
+    #[serde(rename = "highAlarm")]
+    /// The alarm is raised when the value rises above this.
+    pub high_alarm: Option<f64>,
+    #[serde(rename = "highAlarmHysteresis")]
+    /// How far the value has to drop below [`Alarm::high_alarm`] again before the alarm is cleared.
+    pub high_alarm_hysteresis: Option<f64>,
+    #[serde(rename = "AlarmEnabled", with = "crate::installations::int_bool")]
+    pub enabled: bool,
+    #[serde(rename = "NotifyAfterSeconds")]
+    /// How many seconds the value has to be out of range before a notification is sent.
+    pub notify_after_seconds: Option<i32>,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A data attribute watched by an alarm.
+pub struct AlarmAttribute {
+    #[serde(rename = "idDataAttribute")]
+    pub data_id: DataAttributeId,
+    pub code: String,
+    pub description: String,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
@@ -0,0 +1,402 @@
+//! A synchronous client, for programs that don't run an async runtime.
+//!
+//! [`Victron`] mirrors the async [`crate::Victron`] and returns the same models. Each client owns a small
+//! single-threaded runtime that drives the async client, which is shared between its clones.
+//!
+//! The methods in this module block the current thread, so they must not be called from within an async runtime.
+//!
+//! ```no_run
+//! use victron_energy_api::blocking::Victron;
+//!
+//! let client = Victron::login_access_token("me@example.com", "token")?;
+//!
+//! for installation in client.get_all_installations_or_sites(false)? {
+//!     println!("{}: {}", installation.site_id, installation.name);
+//! }
+//! # Ok::<(), victron_energy_api::Error>(())
+//! ```
+
+use std::{fmt, future::Future, sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::runtime::{Builder, Runtime};
+
+use crate::{
+    alarms::Alarms,
+    credentials::Credentials,
+    diagnostics::Diagnostic,
+    ids::{SiteId, UserId},
+    installations::Installation,
+    login::{self, VerificationMode},
+    query::Query,
+    rate_limit::RateLimit,
+    session::Session,
+    stats::{Stats, StatsRequest},
+    transport::{HttpTransport, ReqwestTransport},
+    users::User,
+    watch::InstallationChanges,
+    Error, Method, VictronBuilder,
+};
+
+/// A blocking client for the VRM API. See [`crate::Victron`] for the async client.
+#[derive(Debug, Clone)]
+pub struct Victron<T = ReqwestTransport> {
+    inner: crate::Victron<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl Victron {
+    /// Logs into the Victron API. See [`crate::Victron::login`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    /// - `Error::VerificationRequired` if a verification code is needed, but `sms_token` was not given.
+    pub fn login(
+        username: &str,
+        password: &str,
+        sms_token: Option<&str>,
+        remember_me: bool,
+    ) -> Result<Self, Error> {
+        Self::connect(VictronBuilder::new(), |builder| {
+            builder.login(username, password, sms_token, remember_me)
+        })
+    }
+
+    /// Starts logging in, for accounts that may have two-factor authentication enabled.
+    /// See [`crate::Victron::begin_login`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub fn begin_login(
+        username: &str,
+        password: &str,
+        remember_me: bool,
+    ) -> Result<LoginOutcome, Error> {
+        let runtime = Arc::new(runtime()?);
+        let outcome =
+            runtime.block_on(VictronBuilder::new().begin_login(username, password, remember_me))?;
+
+        Ok(match outcome {
+            login::LoginOutcome::LoggedIn(inner) => LoginOutcome::LoggedIn(Self { inner, runtime }),
+            login::LoginOutcome::VerificationRequired(inner) => {
+                LoginOutcome::VerificationRequired(PendingVerification { inner, runtime })
+            }
+        })
+    }
+
+    /// Logs into the Victron API with an access token. See [`crate::Victron::login_access_token`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub fn login_access_token(username: &str, access_token: &str) -> Result<Self, Error> {
+        Self::connect(VictronBuilder::new(), |builder| {
+            builder.login_access_token(username, access_token)
+        })
+    }
+
+    /// Logs into the Victron API as a demo user. See [`crate::Victron::login_as_demo`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed.
+    pub fn login_as_demo() -> Result<Self, Error> {
+        Self::connect(VictronBuilder::new(), VictronBuilder::login_as_demo)
+    }
+
+    /// Restores a client from a session saved with [`Victron::session`], without logging in again.
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    pub fn from_session(session: Session) -> Result<Self, Error> {
+        Self::new(crate::Victron::from_session(session))
+    }
+}
+
+impl<T: HttpTransport> Victron<T> {
+    /// Wraps an async client, for example one created with a custom transport through [`VictronBuilder`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    pub fn new(inner: crate::Victron<T>) -> Result<Self, Error> {
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime()?),
+        })
+    }
+
+    /// Starts a runtime and uses it to log in with `builder`.
+    fn connect<F, Fut>(builder: VictronBuilder<T>, login: F) -> Result<Self, Error>
+    where
+        F: FnOnce(VictronBuilder<T>) -> Fut,
+        Fut: Future<Output = Result<crate::Victron<T>, Error>>,
+    {
+        let runtime = runtime()?;
+        let inner = runtime.block_on(login(builder))?;
+
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    #[must_use]
+    /// Returns the async client this client wraps.
+    ///
+    /// Its connections are driven by the runtime of this client, which only runs while a method of this client
+    /// blocks, so requests sent through it from another runtime can stall. Create a separate async client, for
+    /// example from [`Victron::session`], to send requests from another runtime.
+    pub const fn as_async(&self) -> &crate::Victron<T> {
+        &self.inner
+    }
+
+    #[must_use]
+    /// Limits how many requests this client may send. See [`crate::Victron::with_rate_limit`].
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.inner = self.inner.with_rate_limit(limit);
+        self
+    }
+
+    #[must_use]
+    /// Sets the credentials used to log in again when the token is rejected.
+    /// See [`crate::Victron::with_credentials`].
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.inner = self.inner.with_credentials(credentials);
+        self
+    }
+
+    #[must_use]
+    /// Returns the current session, so it can be saved and restored later. See [`crate::Victron::session`].
+    pub fn session(&self) -> Session {
+        self.runtime.block_on(self.inner.session())
+    }
+
+    /// Gets the user id of the currently logged in user. See [`crate::Victron::ensure_user_id`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn ensure_user_id(&self) -> Result<UserId, Error> {
+        self.runtime.block_on(self.inner.ensure_user_id())
+    }
+
+    /// Adds a new site to the user. See [`crate::Victron::add_new_site`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn add_new_site(&self, identifier: &str) -> Result<SiteId, Error> {
+        self.runtime.block_on(self.inner.add_new_site(identifier))
+    }
+
+    /// Retrieves a list of installations to which the user is connected.
+    /// See [`crate::Victron::get_all_installations_or_sites`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn get_all_installations_or_sites(
+        &self,
+        extended: bool,
+    ) -> Result<Vec<Installation>, Error> {
+        self.runtime
+            .block_on(self.inner.get_all_installations_or_sites(extended))
+    }
+
+    /// Retrieves a specific installation or site by its id. See [`crate::Victron::get_installation_or_site`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    /// - [`Error::InstallationNotFound`] if the user has no installation with this id.
+    pub fn get_installation_or_site(
+        &self,
+        extended: bool,
+        site_id: SiteId,
+    ) -> Result<Installation, Error> {
+        self.runtime
+            .block_on(self.inner.get_installation_or_site(extended, site_id))
+    }
+
+    /// Retrieves id, name, email and country of the user that is currently logged in.
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn get_user_info(&self) -> Result<User, Error> {
+        self.runtime.block_on(self.inner.get_user_info())
+    }
+
+    /// Retrieves statistics of an installation over a time range. See [`crate::Victron::get_stats`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn get_stats(&self, site_id: SiteId, request: &StatsRequest) -> Result<Stats, Error> {
+        self.runtime
+            .block_on(self.inner.get_stats(site_id, request))
+    }
+
+    /// Retrieves the most recent value of every data attribute of an installation.
+    /// See [`crate::Victron::get_diagnostics`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn get_diagnostics(&self, site_id: SiteId) -> Result<Vec<Diagnostic>, Error> {
+        self.runtime.block_on(self.inner.get_diagnostics(site_id))
+    }
+
+    /// Retrieves the alarms configured for an installation. See [`crate::Victron::get_alarms`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn get_alarms(&self, site_id: SiteId) -> Result<Alarms, Error> {
+        self.runtime.block_on(self.inner.get_alarms(site_id))
+    }
+
+    /// Polls an installation every `interval`, yielding what changed since the previous poll.
+    /// See [`crate::Victron::watch_installation`].
+    ///
+    /// Every call to [`Iterator::next`] blocks until something changed or a poll failed.
+    pub fn watch_installation(
+        &self,
+        site_id: SiteId,
+        interval: Duration,
+    ) -> impl Iterator<Item = Result<InstallationChanges, Error>> + '_ {
+        let mut changes = Box::pin(self.inner.watch_installation(site_id, interval));
+
+        std::iter::from_fn(move || self.runtime.block_on(changes.next()))
+    }
+
+    /// Sends a request to an endpoint this library doesn't wrap yet. See [`crate::Victron::request`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    /// - [`Error::Json`] if the response could not be deserialized into `R`.
+    pub fn request<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &Query,
+        body: Option<&Value>,
+    ) -> Result<R, Error> {
+        self.runtime
+            .block_on(self.inner.request(method, path, query, body))
+    }
+
+    /// Sends a `GET` request and returns the response as JSON. See [`crate::Victron::raw_get`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub fn raw_get(&self, path: &str, query: &Query) -> Result<Value, Error> {
+        self.runtime.block_on(self.inner.raw_get(path, query))
+    }
+}
+
+impl<T: HttpTransport> VictronBuilder<T> {
+    /// Logs into the Victron API and returns a blocking client. See [`crate::Victron::login`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    /// - `Error::VerificationRequired` if a verification code is needed, but `sms_token` was not given.
+    pub fn login_blocking(
+        self,
+        username: &str,
+        password: &str,
+        sms_token: Option<&str>,
+        remember_me: bool,
+    ) -> Result<Victron<T>, Error> {
+        Victron::connect(self, |builder| {
+            builder.login(username, password, sms_token, remember_me)
+        })
+    }
+
+    /// Logs into the Victron API with an access token and returns a blocking client.
+    /// See [`crate::Victron::login_access_token`].
+    ///
+    /// # Errors
+    /// - `Error::Io` if the runtime could not be started.
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub fn login_access_token_blocking(
+        self,
+        username: &str,
+        access_token: &str,
+    ) -> Result<Victron<T>, Error> {
+        Victron::connect(self, |builder| {
+            builder.login_access_token(username, access_token)
+        })
+    }
+}
+
+/// The result of [`Victron::begin_login`].
+#[derive(Debug)]
+pub enum LoginOutcome<T = ReqwestTransport> {
+    /// The credentials were accepted and no second factor is needed.
+    LoggedIn(Victron<T>),
+    /// The credentials were accepted, but a verification code has to be submitted with
+    /// [`PendingVerification::verify`] to finish logging in.
+    VerificationRequired(PendingVerification<T>),
+}
+
+/// A login that is waiting for a two-factor verification code. See [`login::PendingVerification`].
+pub struct PendingVerification<T = ReqwestTransport> {
+    inner: login::PendingVerification<T>,
+    runtime: Arc<Runtime>,
+}
+
+impl<T: HttpTransport> PendingVerification<T> {
+    #[must_use]
+    /// How the verification code is delivered to the user.
+    pub const fn mode(&self) -> &VerificationMode {
+        self.inner.mode()
+    }
+
+    #[must_use]
+    /// True if VRM has sent the verification code, for example by SMS.
+    pub const fn code_sent(&self) -> bool {
+        self.inner.code_sent()
+    }
+
+    /// Submits the SMS or TOTP verification code to finish logging in.
+    ///
+    /// If the code is wrong, this can be called again with another code.
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::InvalidVerificationCode` if VRM did not accept the code.
+    pub fn verify(&self, code: &str) -> Result<Victron<T>, Error> {
+        let inner = self.runtime.block_on(self.inner.verify(code))?;
+
+        Ok(Victron {
+            inner,
+            runtime: Arc::clone(&self.runtime),
+        })
+    }
+}
+
+impl<T> fmt::Debug for PendingVerification<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+/// Starts the runtime that drives a blocking client.
+fn runtime() -> Result<Runtime, Error> {
+    Ok(Builder::new_current_thread().enable_all().build()?)
+}
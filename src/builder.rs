@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use crate::{
+    ids::UserId,
+    session::{Session, TokenKind},
+    transport::{DefaultTransport, HttpTransport},
+    Token, Victron, BASE_URL,
+};
+
+#[derive(Debug, Clone)]
+/// Configures how a [`Victron`] client connects to VRM, before logging in.
+///
+/// [`Victron::login`] and the other constructors on [`Victron`] use the default configuration.
+pub struct VictronBuilder<T = DefaultTransport> {
+    pub(crate) transport: T,
+    pub(crate) base_url: Arc<str>,
+    /// The TLS options set with `tls`, which MQTT connections use as well.
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    pub(crate) tls: Option<crate::tls::TlsConfig>,
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl VictronBuilder {
+    #[must_use]
+    /// Creates a builder that sends requests with [`ReqwestTransport`](crate::transport::ReqwestTransport) to the public VRM API.
+    pub fn new() -> Self {
+        Self::with_transport(crate::transport::ReqwestTransport::default())
+    }
+
+    #[cfg(all(
+        any(feature = "rustls", feature = "native-tls"),
+        not(target_arch = "wasm32")
+    ))]
+    /// Replaces the transport with a [`ReqwestTransport`](crate::transport::ReqwestTransport) that connects using the given TLS options.
+    ///
+    /// With the `mqtt` feature, `Victron::connect_mqtt` uses these options too, unless it is given its own.
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if a certificate or identity could not be read, or the client could not be built.
+    pub fn tls(self, tls: &crate::tls::TlsConfig) -> Result<Self, crate::Error> {
+        let builder = self.transport(crate::transport::ReqwestTransport::with_tls(tls)?);
+
+        #[cfg(feature = "mqtt")]
+        let builder = Self {
+            tls: Some(tls.clone()),
+            ..builder
+        };
+
+        Ok(builder)
+    }
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl Default for VictronBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: HttpTransport> VictronBuilder<T> {
+    #[must_use]
+    /// Creates a builder that sends requests through `transport` to the public VRM API.
+    pub fn with_transport(transport: T) -> Self {
+        Self {
+            transport,
+            base_url: Arc::from(BASE_URL),
+            #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+            tls: None,
+        }
+    }
+
+    #[must_use]
+    /// Replaces the transport requests are sent through.
+    pub fn transport<U: HttpTransport>(self, transport: U) -> VictronBuilder<U> {
+        VictronBuilder {
+            transport,
+            base_url: self.base_url,
+            #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+            tls: self.tls,
+        }
+    }
+
+    #[must_use]
+    /// Sets the base URL of the VRM API, for example to point at a proxy or a test server.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Arc::from(base_url.trim_end_matches('/'));
+        self
+    }
+
+    #[must_use]
+    /// Builds a client from a token that was obtained elsewhere.
+    pub fn with_token(self, token: Token, user_id: Option<UserId>) -> Victron<T> {
+        Victron::new(self, token, user_id)
+    }
+
+    #[must_use]
+    /// Rebuilds a client from a [`Session`], using the base URL stored in the session.
+    ///
+    /// This does not check whether the session is still valid. Use [`Session::is_expired`] to check the expiry
+    /// beforehand, or [`Victron::with_credentials`] to log in again once VRM rejects the token.
+    pub fn restore(self, session: Session) -> Victron<T> {
+        let token = match session.token_kind {
+            TokenKind::Bearer => Token::Bearer(session.token),
+            TokenKind::Access => Token::Access(session.token),
+        };
+
+        self.base_url(&session.base_url)
+            .with_token(token, session.user_id)
+    }
+}
@@ -0,0 +1,365 @@
+//! Record-and-replay of HTTP interactions.
+//!
+//! A [`Cassette`] wraps another [`HttpTransport`]. In record mode it forwards every request and writes the
+//! request and response to a JSON file. In replay mode it answers requests from that file without touching the
+//! network, and fails any request that wasn't recorded.
+//!
+//! Usernames, tokens, passwords and verification codes are redacted before anything is written, in JSON bodies as
+//! well as form-encoded ones.
+//!
+//! ```no_run
+//! # #[cfg(feature = "tokio")]
+//! # async fn example() -> Result<(), victron_energy_api::Error> {
+//! use victron_energy_api::{cassette::Cassette, transport::ReqwestTransport, VictronBuilder};
+//!
+//! // Records the first time it runs, and replays the recording every time after that.
+//! let cassette = Cassette::auto(ReqwestTransport::new(), "tests/cassettes/user_info.json")?;
+//!
+//! let victron = VictronBuilder::with_transport(cassette)
+//!     .login("john@example.com", "password", None, false)
+//!     .await?;
+//! let user = victron.get_user_info().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use url::form_urlencoded;
+
+use crate::{
+    transport::{DefaultTransport, HttpRequest, HttpResponse, HttpTransport},
+    Error,
+};
+
+/// The value that replaces redacted secrets.
+const REDACTED: &str = "REDACTED";
+
+/// Keys whose values are redacted, wherever they appear in a JSON body, or as fields of a form-encoded body.
+const SECRET_KEYS: &[&str] = &["username", "token", "password", "sms_token", "access_token"];
+
+/// Headers whose values are redacted.
+const SECRET_HEADERS: &[&str] = &["x-authorization", "authorization", "cookie", "set-cookie"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Whether a [`Cassette`] records or replays interactions.
+pub enum CassetteMode {
+    /// Requests are sent through the wrapped transport, and recorded.
+    Record,
+    /// Requests are answered from the recording.
+    Replay,
+}
+
+#[derive(Debug, thiserror::Error)]
+/// Errors specific to replaying a cassette, returned as [`Error::Transport`].
+pub enum CassetteError {
+    #[error("no recorded interaction matches {method} {url}")]
+    Unmatched { method: String, url: String },
+
+    #[error("the cassette is in record mode, but has no transport to record from")]
+    NoTransport,
+}
+
+#[derive(Debug, Clone)]
+/// An [`HttpTransport`] that records or replays interactions. See the [module documentation](self).
+pub struct Cassette<T = DefaultTransport> {
+    inner: Option<T>,
+    mode: CassetteMode,
+    path: Arc<Path>,
+    state: Arc<Mutex<CassetteState>>,
+}
+
+#[derive(Debug, Default)]
+struct CassetteState {
+    interactions: Vec<Interaction>,
+    /// Which recorded interactions have already been replayed.
+    used: Vec<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A recorded request and its response.
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub body: Option<Body>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    pub body: Body,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A recorded body, stored as JSON when it is JSON so the cassette stays readable.
+pub enum Body {
+    Json(Value),
+    Text(String),
+}
+
+impl Body {
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+
+        Some(serde_json::from_slice::<Value>(bytes).map_or_else(
+            |_| Self::Text(redact_text(&String::from_utf8_lossy(bytes))),
+            |mut value| {
+                redact(&mut value);
+                Self::Json(value)
+            },
+        ))
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Json(value) => value.to_string().into_bytes(),
+            Self::Text(text) => text.into_bytes(),
+        }
+    }
+}
+
+impl CassetteState {
+    /// Returns the response of the first unused interaction recorded for `request`, and marks it as used.
+    fn take(&mut self, request: &RecordedRequest) -> Option<RecordedResponse> {
+        let index = self
+            .interactions
+            .iter()
+            .zip(&self.used)
+            .position(|(interaction, used)| !used && interaction.request == *request)?;
+
+        self.used[index] = true;
+        Some(self.interactions[index].response.clone())
+    }
+}
+
+impl<T: HttpTransport> Cassette<T> {
+    /// Records every interaction sent through `inner` to the file at `path`, replacing what it contained.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the directory of the cassette could not be created.
+    pub fn record(inner: T, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(Self {
+            inner: Some(inner),
+            mode: CassetteMode::Record,
+            path: Arc::from(path),
+            state: Arc::default(),
+        })
+    }
+
+    /// Replays the cassette at `path` if it exists, or records a new one through `inner` if it doesn't.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the cassette exists but could not be read.
+    /// - [`Error::Json`] if the cassette is not valid.
+    pub fn auto(inner: T, path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+
+        if path.exists() {
+            let mut cassette = Self::load(path)?;
+            cassette.inner = Some(inner);
+            Ok(cassette)
+        } else {
+            Self::record(inner, path)
+        }
+    }
+
+    fn load(path: PathBuf) -> Result<Self, Error> {
+        let interactions: Vec<Interaction> = serde_json::from_slice(&fs::read(&path)?)?;
+
+        Ok(Self {
+            inner: None,
+            mode: CassetteMode::Replay,
+            path: Arc::from(path),
+            state: Arc::new(Mutex::new(CassetteState {
+                used: vec![false; interactions.len()],
+                interactions,
+            })),
+        })
+    }
+
+    #[must_use]
+    pub const fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    #[must_use]
+    /// Returns the interactions recorded or loaded so far.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state().interactions.clone()
+    }
+
+    fn state(&self) -> MutexGuard<'_, CassetteState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    async fn record_interaction(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let inner = self
+            .inner
+            .as_ref()
+            .ok_or_else(|| Error::Transport(Box::new(CassetteError::NoTransport)))?;
+
+        let recorded_request = record_request(&request);
+        let response = inner.send(request).await?;
+
+        let interaction = Interaction {
+            request: recorded_request,
+            response: RecordedResponse {
+                status: response.status().as_u16(),
+                headers: response
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| {
+                        let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                            REDACTED
+                        } else {
+                            value.to_str().unwrap_or_default()
+                        };
+
+                        (name.to_string(), value.to_string())
+                    })
+                    .collect(),
+                body: Body::from_bytes(response.body()).unwrap_or(Body::Text(String::new())),
+            },
+        };
+
+        // The file is written while the state is locked, so a concurrent request can't replace it with an older
+        // snapshot.
+        let mut state = self.state();
+        state.interactions.push(interaction);
+        state.used.push(true);
+        write_atomically(&self.path, &serde_json::to_vec_pretty(&state.interactions)?)?;
+        drop(state);
+
+        Ok(response)
+    }
+
+    fn replay_interaction(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let recorded_request = record_request(request);
+
+        let Some(recorded_response) = self.state().take(&recorded_request) else {
+            return Err(Error::Transport(Box::new(CassetteError::Unmatched {
+                method: recorded_request.method,
+                url: recorded_request.url,
+            })));
+        };
+
+        let mut response = HttpResponse::new(recorded_response.body.into_bytes());
+        *response.status_mut() = StatusCode::from_u16(recorded_response.status)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        for (name, value) in &recorded_response.headers {
+            if let (Ok(name), Ok(value)) = (
+                http::HeaderName::try_from(name.as_str()),
+                http::HeaderValue::try_from(value.as_str()),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl Cassette {
+    /// Replays the cassette at `path`, failing any request that wasn't recorded.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the cassette could not be read.
+    /// - [`Error::Json`] if the cassette is not valid.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        Self::load(path.into())
+    }
+}
+
+impl<T: HttpTransport> HttpTransport for Cassette<T> {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        match self.mode {
+            CassetteMode::Record => self.record_interaction(request).await,
+            CassetteMode::Replay => self.replay_interaction(&request),
+        }
+    }
+}
+
+fn record_request(request: &HttpRequest) -> RecordedRequest {
+    RecordedRequest {
+        method: request.method().to_string(),
+        url: request.uri().to_string(),
+        body: Body::from_bytes(request.body()),
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path`, and renames it over `path`, so the cassette is never
+/// left half written.
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)?;
+
+    Ok(())
+}
+
+/// Replaces the values of secret keys anywhere in `value`.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if SECRET_KEYS.contains(&key.as_str()) && !value.is_null() {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(array) => array.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Replaces the values of secret fields if `text` is form-encoded, and returns it unchanged otherwise.
+fn redact_text(text: &str) -> String {
+    let fields: Vec<_> = form_urlencoded::parse(text.as_bytes()).collect();
+
+    if !fields
+        .iter()
+        .any(|(key, _)| SECRET_KEYS.contains(&key.as_ref()))
+    {
+        return text.to_string();
+    }
+
+    form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(fields.iter().map(|(key, value)| {
+            if SECRET_KEYS.contains(&key.as_ref()) {
+                (key.as_ref(), REDACTED)
+            } else {
+                (key.as_ref(), value.as_ref())
+            }
+        }))
+        .finish()
+}
@@ -0,0 +1,140 @@
+use std::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use crate::{
+    transport::{HttpTransport, MaybeSend},
+    Error, Token, VictronBuilder,
+};
+
+/// A future returned by a [`Credentials::Callback`].
+#[cfg(not(target_arch = "wasm32"))]
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<Token, Error>> + Send>>;
+
+/// A future returned by a [`Credentials::Callback`].
+#[cfg(target_arch = "wasm32")]
+pub type TokenFuture = Pin<Box<dyn Future<Output = Result<Token, Error>>>>;
+
+/// Credentials used to log in again when the current token is rejected.
+///
+/// See [`Victron::with_credentials`](crate::Victron::with_credentials).
+#[derive(Clone)]
+pub enum Credentials {
+    /// Log in again with a username and password, like [`Victron::login`](crate::Victron::login).
+    Password { username: String, password: String },
+    /// Log in again with an access token, like [`Victron::login_access_token`](crate::Victron::login_access_token).
+    AccessToken {
+        username: String,
+        access_token: String,
+    },
+    /// Call a user-provided function to obtain a fresh token.
+    ///
+    /// The function may use the client, for example to read [`Victron::session`](crate::Victron::session).
+    /// Requests it sends that are rejected as well return the rejection, instead of logging in again. Requests it
+    /// leaves to other tasks, such as with `tokio::spawn`, wait for it to finish like any other request, so it must
+    /// not wait for those.
+    Callback(Arc<dyn Fn() -> TokenFuture + Send + Sync>),
+}
+
+impl Credentials {
+    #[must_use]
+    /// Creates [`Credentials::Callback`] from an async function.
+    pub fn callback<F, Fut>(callback: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Token, Error>> + MaybeSend + 'static,
+    {
+        Self::Callback(Arc::new(move || Box::pin(callback())))
+    }
+
+    /// Obtains a fresh token using these credentials.
+    pub(crate) async fn authenticate<T: HttpTransport>(
+        &self,
+        builder: &VictronBuilder<T>,
+    ) -> Result<Token, Error> {
+        match self {
+            Self::Password { username, password } => Ok(builder
+                .request_login(username, password, None, false)
+                .await?
+                .0),
+            Self::AccessToken {
+                username,
+                access_token,
+            } => Ok(builder
+                .request_access_token_login(username, access_token)
+                .await?
+                .0),
+            Self::Callback(callback) => callback().await,
+        }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Password { username, .. } => f
+                .debug_struct("Password")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Self::AccessToken { username, .. } => f
+                .debug_struct("AccessToken")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Self::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+thread_local! {
+    /// The clients that are logging in again on this thread, innermost last. See [`Refreshing`].
+    static REFRESHING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns whether the client identified by `client` is logging in again within the current poll, which means the
+/// caller is a request sent by the login itself.
+pub(crate) fn is_refreshing(client: usize) -> bool {
+    REFRESHING.with_borrow(|clients| clients.contains(&client))
+}
+
+/// A login of the client identified by `client`, which marks the client as logging in again while it is polled.
+///
+/// The requests a login sends itself are polled within it, so [`is_refreshing`] tells them apart from requests of
+/// other tasks, on any executor.
+pub(crate) struct Refreshing<F> {
+    client: usize,
+    future: Pin<Box<F>>,
+}
+
+impl<F: Future> Refreshing<F> {
+    pub(crate) fn new(client: usize, future: F) -> Self {
+        Self {
+            client,
+            future: Box::pin(future),
+        }
+    }
+}
+
+impl<F: Future> Future for Refreshing<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        /// Unmarks the client when polling ends, even if it panics.
+        struct Unmark;
+
+        impl Drop for Unmark {
+            fn drop(&mut self) {
+                REFRESHING.with_borrow_mut(Vec::pop);
+            }
+        }
+
+        REFRESHING.with_borrow_mut(|clients| clients.push(self.client));
+        let _unmark = Unmark;
+
+        self.future.as_mut().poll(cx)
+    }
+}
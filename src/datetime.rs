@@ -0,0 +1,139 @@
+//! Conversions between the UNIX timestamps used by VRM and date-time types.
+//!
+//! [`SystemTime`] is always supported. Enable the `chrono` or `time` feature to use their date-time types as well.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A date-time type that can be built from a UNIX timestamp and the UTC offset of an installation.
+pub trait FromTimestamp: Sized {
+    /// Builds a date-time from seconds since the UNIX epoch, in a time zone `offset_secs` seconds from UTC.
+    ///
+    /// Returns `None` if the timestamp or offset is out of range.
+    fn from_timestamp(secs: i64, offset_secs: i32) -> Option<Self>;
+}
+
+/// A time-of-day type that can be built from hours and minutes.
+pub trait FromTimeOfDay: Sized {
+    /// Returns `None` if the time is out of range.
+    fn from_hm(hour: u8, minute: u8) -> Option<Self>;
+}
+
+/// A date-time that can be passed to VRM as a UNIX timestamp, for example as part of a [`TimeRange`].
+pub trait IntoTimestamp {
+    /// Returns the number of seconds since the UNIX epoch.
+    fn into_timestamp(self) -> i64;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// A range of time, used by endpoints that return data for a period.
+pub struct TimeRange {
+    /// The start of the range, UNIX timestamp.
+    pub start: i64,
+    /// The end of the range, UNIX timestamp.
+    pub end: i64,
+}
+
+impl TimeRange {
+    #[must_use]
+    pub fn new(start: impl IntoTimestamp, end: impl IntoTimestamp) -> Self {
+        Self {
+            start: start.into_timestamp(),
+            end: end.into_timestamp(),
+        }
+    }
+
+    #[must_use]
+    /// Returns the range covering the `duration` leading up to now.
+    pub fn last(duration: Duration) -> Self {
+        let now = crate::runtime::unix_time();
+        let secs = |since_epoch: Duration| i64::try_from(since_epoch.as_secs()).unwrap_or(i64::MAX);
+
+        Self {
+            start: secs(now.saturating_sub(duration)),
+            end: secs(now),
+        }
+    }
+}
+
+impl FromTimestamp for SystemTime {
+    fn from_timestamp(secs: i64, _offset_secs: i32) -> Option<Self> {
+        let offset = Duration::from_secs(secs.unsigned_abs());
+
+        if secs >= 0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        }
+    }
+}
+
+impl IntoTimestamp for SystemTime {
+    fn into_timestamp(self) -> i64 {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()).unwrap_or(i64::MAX),
+            Err(before) => {
+                i64::try_from(before.duration().as_secs()).map_or(i64::MIN, |secs| -secs)
+            }
+        }
+    }
+}
+
+impl IntoTimestamp for i64 {
+    fn into_timestamp(self) -> i64 {
+        self
+    }
+}
+
+#[cfg(feature = "chrono")]
+mod chrono_impls {
+    use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone};
+
+    use super::{FromTimeOfDay, FromTimestamp, IntoTimestamp};
+
+    impl FromTimestamp for DateTime<FixedOffset> {
+        fn from_timestamp(secs: i64, offset_secs: i32) -> Option<Self> {
+            FixedOffset::east_opt(offset_secs)?
+                .timestamp_opt(secs, 0)
+                .single()
+        }
+    }
+
+    impl FromTimeOfDay for NaiveTime {
+        fn from_hm(hour: u8, minute: u8) -> Option<Self> {
+            Self::from_hms_opt(hour.into(), minute.into(), 0)
+        }
+    }
+
+    impl<Tz: TimeZone> IntoTimestamp for DateTime<Tz> {
+        fn into_timestamp(self) -> i64 {
+            self.timestamp()
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+mod time_impls {
+    use time::{OffsetDateTime, Time, UtcOffset};
+
+    use super::{FromTimeOfDay, FromTimestamp, IntoTimestamp};
+
+    impl FromTimestamp for OffsetDateTime {
+        fn from_timestamp(secs: i64, offset_secs: i32) -> Option<Self> {
+            let offset = UtcOffset::from_whole_seconds(offset_secs).ok()?;
+
+            Some(Self::from_unix_timestamp(secs).ok()?.to_offset(offset))
+        }
+    }
+
+    impl FromTimeOfDay for Time {
+        fn from_hm(hour: u8, minute: u8) -> Option<Self> {
+            Self::from_hms(hour, minute, 0).ok()
+        }
+    }
+
+    impl IntoTimestamp for OffsetDateTime {
+        fn into_timestamp(self) -> i64 {
+            self.unix_timestamp()
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    datetime::FromTimestamp,
+    endpoint::Endpoint,
+    ids::{DataAttributeId, SiteId},
+    installations::{AttributeValue, DataAttributeEnumValue},
+    query::Query,
+    transport::HttpTransport,
+    Error, Method, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
+    /// Retrieves the most recent value of every data attribute of every device in an installation, as shown on
+    /// the Advanced and Device list pages of VRM.
+    ///
+    /// VRM returns at most 1000 records per request, so large installations take several requests.
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending a request.
+    /// - [`Error::Victron`] if a request failed.
+    pub async fn get_diagnostics(&self, site_id: SiteId) -> Result<Vec<Diagnostic>, Error> {
+        let mut records = Vec::new();
+        let mut endpoint = DiagnosticsEndpoint {
+            site_id,
+            count: DiagnosticsEndpoint::PAGE_SIZE,
+            offset: 0,
+        };
+
+        loop {
+            let page = self.execute(&endpoint).await?;
+            let received = page.records.len();
+            records.extend(page.records);
+
+            // Older responses have no total, so a page that isn't full is the last one as well.
+            let complete = page.num_records.is_some_and(|total| records.len() >= total);
+            if complete || received < endpoint.count as usize {
+                return Ok(records);
+            }
+
+            endpoint.offset += endpoint.count;
+        }
+    }
+}
+
+struct DiagnosticsEndpoint {
+    site_id: SiteId,
+    count: u32,
+    offset: u32,
+}
+
+impl DiagnosticsEndpoint {
+    /// The most records VRM returns at once.
+    const PAGE_SIZE: u32 = 1000;
+}
+
+impl Endpoint for DiagnosticsEndpoint {
+    type Response = DiagnosticsSuccess;
+
+    const METHOD: Method = Method::GET;
+
+    fn path(&self) -> String {
+        format!("/installations/{}/diagnostics", self.site_id)
+    }
+
+    fn query(&self) -> Query {
+        Query::new()
+            .param("count", self.count)
+            .param("offset", self.offset)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsSuccess {
+    pub success: bool,
+    pub records: Vec<Diagnostic>,
+    #[serde(default)]
+    /// How many records the installation has in total, over all pages.
+    pub num_records: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The most recent value of a data attribute of a device.
+pub struct Diagnostic {
+    #[serde(rename = "idSite")]
+    pub site_id: SiteId,
+    /// When the value was logged, UNIX timestamp
+    pub timestamp: i64,
+    #[serde(rename = "Device")]
+    /// The kind of device, such as `Gateway` or `Solar Charger`.
+    pub device: String,
+    pub instance: i32,
+    #[serde(rename = "idDataAttribute")]
+    pub data_id: DataAttributeId,
+    pub description: String,
+    #[serde(rename = "formatWithUnit")]
+    pub format_with_unit: String,
+    #[serde(rename = "dbusServiceType")]
+    pub dbus_service_type: Option<String>,
+    #[serde(rename = "dbusPath")]
+    pub dbus_path: Option<String>,
+    pub code: String,
+    #[serde(default)]
+    pub bitmask: i32,
+    #[serde(rename = "formattedValue")]
+    pub formatted_value: String,
+    #[serde(rename = "rawValue")]
+    /// The raw value, which VRM sends as a number or a string depending on the attribute.
+    pub raw_value: Value,
+    #[serde(rename = "dataAttributeEnumValues", default)]
+    /// The names of the values an enum attribute can have.
+    pub data_attribute_enum_values: Vec<DataAttributeEnumValue>,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
+}
+
+impl Diagnostic {
+    #[must_use]
+    /// Returns when the value was logged, in UTC.
+    pub fn timestamp_at<T: FromTimestamp>(&self) -> Option<T> {
+        T::from_timestamp(self.timestamp, 0)
+    }
+
+    #[must_use]
+    /// Returns the value, named using [`Diagnostic::data_attribute_enum_values`] if the attribute is an enum.
+    pub fn value(&self) -> Option<AttributeValue> {
+        if !self.data_attribute_enum_values.is_empty() {
+            let value = match &self.raw_value {
+                Value::Number(number) => i32::try_from(number.as_i64()?).ok()?,
+                Value::String(text) => text.trim().parse().ok()?,
+                _ => return None,
+            };
+
+            let name = self
+                .data_attribute_enum_values
+                .iter()
+                .find(|enum_value| enum_value.value_enum == value)
+                .map(|enum_value| enum_value.name_enum.clone());
+
+            return Some(AttributeValue::Enum { value, name });
+        }
+
+        match &self.raw_value {
+            Value::Number(number) => number.as_f64().map(AttributeValue::Numeric),
+            Value::String(text) if !text.is_empty() => Some(AttributeValue::Text(text.clone())),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{
+    query::Query,
+    transport::{parse_response, HttpTransport},
+    Error, Method, Victron,
+};
+
+/// A VRM endpoint that can be called with [`Victron::execute`].
+pub trait Endpoint {
+    /// The type the response body is parsed into.
+    type Response: DeserializeOwned;
+
+    /// The HTTP method of the endpoint.
+    const METHOD: Method;
+
+    /// The path of the endpoint, relative to the base URL.
+    fn path(&self) -> String;
+
+    /// The query string parameters of the request.
+    fn query(&self) -> Query {
+        Query::new()
+    }
+
+    /// The JSON body of the request, if it has one.
+    fn body(&self) -> Option<Value> {
+        None
+    }
+}
+
+impl<T: HttpTransport> Victron<T> {
+    /// Calls an endpoint, parsing the response into its response type.
+    pub(crate) async fn execute<E: Endpoint + Sync>(
+        &self,
+        endpoint: &E,
+    ) -> Result<E::Response, Error> {
+        self.request(
+            E::METHOD,
+            &endpoint.path(),
+            &endpoint.query(),
+            endpoint.body().as_ref(),
+        )
+        .await
+    }
+
+    /// Sends an authenticated request to any VRM endpoint, including ones this library doesn't wrap yet.
+    ///
+    /// The request goes through the same rate limiting, re-authentication and error handling as every other method.
+    /// `path` is relative to the base URL, for example `/installations/1234/stats`.
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    /// - [`Error::Json`] if the response could not be parsed as `R`.
+    pub async fn request<R: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        query: &Query,
+        body: Option<&Value>,
+    ) -> Result<R, Error> {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+
+        let resp = self.send(method, &url, query, body).await?;
+
+        parse_response(&resp)
+    }
+
+    /// Sends an authenticated `GET` request to any VRM endpoint, returning the response as JSON.
+    ///
+    /// See [`Victron::request`].
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub async fn raw_get(&self, path: &str, query: &Query) -> Result<Value, Error> {
+        self.request(Method::GET, path, query, None).await
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+
+use http::StatusCode;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{ids::SiteId, login::VerificationMode, transport::HttpResponse};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+    #[error("Reqwest Error: {0}")]
+    Reqwest(#[from] reqwest::Error),
+
+    #[error("HTTP Error: {0}")]
+    Http(#[from] http::Error),
+
+    #[error("Invalid header value: {0}")]
+    InvalidHeader(#[from] http::header::InvalidHeaderValue),
+
+    #[error("Invalid URL: {0}")]
+    Url(#[from] url::ParseError),
+
+    #[error("Transport Error: {0}")]
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+
+    #[error("Victron Error: {0}")]
+    Victron(Failure),
+
+    #[error("Installation {0} was not found")]
+    InstallationNotFound(SiteId),
+
+    #[error("Login requires a verification code ({0:?})")]
+    VerificationRequired(VerificationMode),
+
+    #[error("The verification code was not accepted")]
+    InvalidVerificationCode,
+
+    #[error("IO Error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON Error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("Installation {0} has no MQTT broker")]
+    MqttUnavailable(SiteId),
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("MQTT Error: {0}")]
+    Mqtt(#[from] rumqttc::ClientError),
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("MQTT Connection Error: {0}")]
+    MqttConnection(Box<rumqttc::ConnectionError>),
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("The MQTT connection was closed")]
+    MqttClosed,
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("The GX device did not announce its portal id in time")]
+    MqttDiscoveryTimeout,
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("The user may not change values of this installation over MQTT")]
+    MqttWriteNotPermitted,
+
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    #[error("The GX device did not confirm the change of {0} in time")]
+    MqttWriteTimeout(crate::mqtt::ValueKey),
+}
+
+impl Error {
+    #[must_use]
+    /// Returns the VRM error code, if this error is a failure returned by VRM.
+    pub const fn code(&self) -> Option<&ErrorCode> {
+        match self {
+            Self::Victron(failure) => Some(&failure.code),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A request that VRM, or something in front of it, answered with an error.
+pub struct Failure {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// What went wrong, read from the `error_code` in the response, or the status code if there was none.
+    pub code: ErrorCode,
+    /// The `errors` VRM returned, or [`Value::Null`] if the response wasn't JSON.
+    pub errors: Value,
+    /// The raw response body, if it wasn't a JSON error from VRM.
+    ///
+    /// This is set for things like HTML error pages from proxies, or empty gateway errors.
+    pub body: Option<String>,
+}
+
+/// The error body returned by VRM.
+#[derive(Debug, Deserialize)]
+struct FailureBody {
+    #[serde(default)]
+    errors: Value,
+    #[serde(default)]
+    error_code: Option<String>,
+}
+
+impl Failure {
+    /// Reads a failure from an unsuccessful response, whether or not the body is JSON.
+    pub(crate) fn from_response(resp: &HttpResponse) -> Self {
+        let status = resp.status();
+
+        match serde_json::from_slice::<FailureBody>(resp.body()) {
+            Ok(failure) => Self {
+                status: status.as_u16(),
+                code: ErrorCode::new(failure.error_code.as_deref(), status),
+                errors: failure.errors,
+                body: None,
+            },
+            Err(_) => Self {
+                status: status.as_u16(),
+                code: ErrorCode::new(None, status),
+                errors: Value::Null,
+                body: Some(String::from_utf8_lossy(resp.body()).into_owned()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (HTTP {})", self.code, self.status)?;
+
+        match (&self.errors, &self.body) {
+            (Value::Null, Some(body)) if !body.is_empty() => write!(f, ": {body}"),
+            (Value::Null, _) => Ok(()),
+            (Value::String(errors), _) => write!(f, ": {errors}"),
+            (errors, _) => write!(f, ": {errors}"),
+        }
+    }
+}
+
+#[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+impl From<rumqttc::ConnectionError> for Error {
+    fn from(error: rumqttc::ConnectionError) -> Self {
+        Self::MqttConnection(Box::new(error))
+    }
+}
+
+impl From<Failure> for Error {
+    fn from(failure: Failure) -> Self {
+        Self::Victron(failure)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The reason a request failed.
+pub enum ErrorCode {
+    /// The username, password or access token was not accepted.
+    InvalidCredentials,
+    /// The account has two-factor authentication enabled, and no verification code was given.
+    TwoFactorRequired,
+    /// The user is not allowed to access this resource.
+    Forbidden,
+    /// The resource does not exist.
+    NotFound,
+    /// Too many requests were sent. See [`crate::rate_limit`].
+    RateLimited,
+    /// The token has expired or is otherwise no longer valid.
+    TokenExpired,
+    /// Any other error. Contains the `error_code` returned by VRM, or the HTTP status if there was none.
+    Unknown(String),
+}
+
+impl ErrorCode {
+    fn new(error_code: Option<&str>, status: StatusCode) -> Self {
+        match error_code {
+            Some("invalid_credentials" | "wrong_credentials" | "invalid_login") => {
+                Self::InvalidCredentials
+            }
+            Some("verification_required" | "sms_token_required" | "two_factor_required") => {
+                Self::TwoFactorRequired
+            }
+            Some("forbidden" | "permission_denied") => Self::Forbidden,
+            Some("not_found") => Self::NotFound,
+            Some("rate_limited" | "too_many_requests") => Self::RateLimited,
+            Some("token_expired" | "expired_token" | "invalid_token") => Self::TokenExpired,
+            Some(code) => Self::Unknown(code.to_string()),
+            None => match status {
+                StatusCode::UNAUTHORIZED => Self::TokenExpired,
+                StatusCode::FORBIDDEN => Self::Forbidden,
+                StatusCode::NOT_FOUND => Self::NotFound,
+                StatusCode::TOO_MANY_REQUESTS => Self::RateLimited,
+                status => Self::Unknown(status.to_string()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCredentials => f.write_str("invalid credentials"),
+            Self::TwoFactorRequired => f.write_str("two-factor authentication required"),
+            Self::Forbidden => f.write_str("forbidden"),
+            Self::NotFound => f.write_str("not found"),
+            Self::RateLimited => f.write_str("rate limited"),
+            Self::TokenExpired => f.write_str("token expired"),
+            Self::Unknown(code) => f.write_str(code),
+        }
+    }
+}
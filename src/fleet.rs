@@ -0,0 +1,134 @@
+//! Fetching data for many installations at once, for dealers and installers who manage a fleet.
+//!
+//! A [`Fleet`] sends a request for every installation, a few at a time, and yields each result as soon as it
+//! arrives. A failure for one installation doesn't stop the others.
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//! use victron_energy_api::{rate_limit::RateLimit, Victron};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> Result<(), victron_energy_api::Error> {
+//! let victron = Victron::login_access_token("me@example.com", "token")
+//!     .await?
+//!     .with_rate_limit(RateLimit::VRM);
+//!
+//! let installations = victron.get_all_installations_or_sites(false).await?;
+//! let mut alarms = victron.fleet(&installations).concurrency(4).alarms();
+//!
+//! while let Some(site) = alarms.next().await {
+//!     match site.result {
+//!         Ok(alarms) => println!("{}: {} alarms", site.site_id, alarms.alarms.len()),
+//!         Err(e) => println!("{}: {e}", site.site_id),
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::future::Future;
+
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{
+    alarms::Alarms,
+    diagnostics::Diagnostic,
+    ids::SiteId,
+    installations::Installation,
+    stats::{Stats, StatsRequest},
+    transport::{DefaultTransport, HttpTransport},
+    Error, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
+    #[must_use]
+    /// Prepares requests for every installation in `installations`, such as the result of
+    /// [`Victron::get_all_installations_or_sites`]. See [`Fleet`].
+    pub fn fleet<'a>(&self, installations: impl IntoIterator<Item = &'a Installation>) -> Fleet<T> {
+        Fleet {
+            victron: self.clone(),
+            site_ids: installations
+                .into_iter()
+                .map(|installation| installation.site_id)
+                .collect(),
+            concurrency: Fleet::<T>::DEFAULT_CONCURRENCY,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Requests for many installations, sent with bounded concurrency. Created with [`Victron::fleet`].
+///
+/// Every request goes through the same client, so its rate limit applies to the fleet as a whole.
+pub struct Fleet<T = DefaultTransport> {
+    victron: Victron<T>,
+    site_ids: Vec<SiteId>,
+    concurrency: usize,
+}
+
+impl<T: HttpTransport> Fleet<T> {
+    /// How many requests are in flight at once, unless changed with [`Fleet::concurrency`].
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+
+    #[must_use]
+    /// Sets how many requests may be in flight at once. At least one request is always allowed.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    #[must_use]
+    /// Returns the ids of the installations in the fleet.
+    pub fn site_ids(&self) -> &[SiteId] {
+        &self.site_ids
+    }
+
+    /// Calls `fetch` for every installation, yielding the results in the order they complete.
+    pub fn fetch<R, F, Fut>(&self, fetch: F) -> impl Stream<Item = SiteResult<R>>
+    where
+        F: Fn(Victron<T>, SiteId) -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        let victron = self.victron.clone();
+
+        stream::iter(self.site_ids.clone())
+            .map(move |site_id| {
+                let result = fetch(victron.clone(), site_id);
+
+                async move {
+                    SiteResult {
+                        site_id,
+                        result: result.await,
+                    }
+                }
+            })
+            .buffer_unordered(self.concurrency)
+    }
+
+    /// Fetches the statistics of every installation. See [`Victron::get_stats`].
+    pub fn stats(&self, request: &StatsRequest) -> impl Stream<Item = SiteResult<Stats>> {
+        let request = request.clone();
+
+        self.fetch(move |victron, site_id| {
+            let request = request.clone();
+            async move { victron.get_stats(site_id, &request).await }
+        })
+    }
+
+    /// Fetches the diagnostics of every installation. See [`Victron::get_diagnostics`].
+    pub fn diagnostics(&self) -> impl Stream<Item = SiteResult<Vec<Diagnostic>>> {
+        self.fetch(|victron, site_id| async move { victron.get_diagnostics(site_id).await })
+    }
+
+    /// Fetches the alarms of every installation. See [`Victron::get_alarms`].
+    pub fn alarms(&self) -> impl Stream<Item = SiteResult<Alarms>> {
+        self.fetch(|victron, site_id| async move { victron.get_alarms(site_id).await })
+    }
+}
+
+#[derive(Debug)]
+/// The result of a request for one installation of a [`Fleet`].
+pub struct SiteResult<R> {
+    pub site_id: SiteId,
+    pub result: Result<R, Error>,
+}
@@ -0,0 +1,156 @@
+//! Strongly typed identifiers used by the VRM API.
+//!
+//! VRM returns most ids as numbers, but some endpoints return them as strings, so every id can be deserialized
+//! from either.
+
+use std::{fmt, num::ParseIntError, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+macro_rules! id {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(pub i32);
+
+        impl $name {
+            #[must_use]
+            /// Returns the id as a number.
+            pub const fn get(self) -> i32 {
+                self.0
+            }
+        }
+
+        impl From<i32> for $name {
+            fn from(id: i32) -> Self {
+                Self(id)
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = ParseIntError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                s.trim().parse().map(Self)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                deserializer.deserialize_any(IdVisitor(stringify!($name))).map(Self)
+            }
+        }
+    };
+}
+
+id!(
+    /// The id of an installation, also called a site.
+    SiteId
+);
+id!(
+    /// The id of a VRM user.
+    UserId
+);
+id!(
+    /// The id of a data attribute.
+    DataAttributeId
+);
+id!(
+    /// The id of an installation tag.
+    TagId
+);
+
+/// Accepts an id given as either a number or a string containing a number.
+struct IdVisitor(&'static str);
+
+impl de::Visitor<'_> for IdVisitor {
+    type Value = i32;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a {} as a number or a string", self.0)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        i32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        i32::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        v.trim()
+            .parse()
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn site_id(json: Value) -> Result<SiteId, serde_json::Error> {
+        serde_json::from_value(json)
+    }
+
+    #[test]
+    fn reads_numbers_and_strings() {
+        assert_eq!(site_id(json!(151_734)).expect("number"), SiteId(151_734));
+        assert_eq!(site_id(json!("151734")).expect("string"), SiteId(151_734));
+        assert_eq!(
+            site_id(json!(" 151734\n")).expect("padded"),
+            SiteId(151_734)
+        );
+        assert_eq!(site_id(json!(-1)).expect("negative"), SiteId(-1));
+        assert_eq!(site_id(json!("-1")).expect("negative"), SiteId(-1));
+    }
+
+    #[test]
+    fn rejects_values_that_are_not_ids() {
+        for json in [
+            json!(i64::from(i32::MAX) + 1),
+            json!(i64::from(i32::MIN) - 1),
+            json!(u64::MAX),
+            json!("2147483648"),
+            json!("12a"),
+            json!(""),
+            json!(1.5),
+            json!(null),
+            json!([1]),
+        ] {
+            let error = site_id(json.clone()).expect_err("not an id");
+            assert!(
+                error
+                    .to_string()
+                    .contains("a SiteId as a number or a string"),
+                "unexpected error for {json}: {error}"
+            );
+        }
+    }
+
+    #[test]
+    fn serializes_as_a_number() {
+        assert_eq!(
+            serde_json::to_value(UserId(22)).expect("serializable id"),
+            json!(22)
+        );
+    }
+}
@@ -1,15 +1,20 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::{
+    datetime::{FromTimeOfDay, FromTimestamp},
+    ids::{DataAttributeId, SiteId, TagId, UserId},
+};
+
 #[allow(clippy::struct_excessive_bools)]
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents all information an installation has.
 pub struct Installation {
     #[serde(rename = "idSite")]
-    pub site_id: i32,
+    pub site_id: SiteId,
     #[serde(rename = "accessLevel")]
     /// The access level of the requesting user
-    pub access_level: i32,
+    pub access_level: AccessLevel,
     /// True if the requesting user owns this installation
     pub owner: bool,
     /// True if the requesting user is an admin for this installation
@@ -18,7 +23,7 @@ pub struct Installation {
     pub identifier: String,
     #[serde(rename = "idUser")]
     /// Installation owner's id
-    pub user_id: i32,
+    pub user_id: UserId,
     #[serde(rename = "pvMax")]
     /// Maximum PV for this installation
     pub pv_max: i32,
@@ -33,22 +38,18 @@ pub struct Installation {
     #[serde(rename = "realtimeUpdates")]
     pub realtime_updates: bool,
     #[serde(rename = "hasMains")]
-    pub has_mains: i8,
+    pub has_mains: Presence,
     #[serde(rename = "hasGenerator")]
-    pub has_generator: i8,
+    pub has_generator: Presence,
     #[serde(rename = "noDataAlarmTimeout")]
     /// How many seconds after no installation data is received an alarm should be triggered
     pub no_data_alarm_timeout: Option<i32>,
     #[serde(rename = "alarmMonitoring")]
     /// If alarms and warnings should be sent.
-    ///
-    /// - 0: Nothing is sent out
-    /// - 1: Only alarms
-    /// - 2: Alarms and warnings
-    pub alarm_monitoring: i8,
-    #[serde(rename = "invalidVRMAuthTokenUsedInLogRequest")]
-    /// 1 if an invalid token was used for logging, else 0
-    pub invalid_vrm_auth_token_used_in_log_request: i8,
+    pub alarm_monitoring: AlarmMonitoring,
+    #[serde(rename = "invalidVRMAuthTokenUsedInLogRequest", with = "int_bool")]
+    /// True if an invalid token was used for logging
+    pub invalid_vrm_auth_token_used_in_log_request: bool,
     #[serde(rename = "syscreated")]
     /// Installation creation timestamp, UNIX timestamp
     pub sys_created: i64,
@@ -63,7 +64,7 @@ pub struct Installation {
     /// The current time of the installation in 24h format (hh:mm)
     pub current_time: Option<String>,
     #[serde(default)]
-    /// How many seconds the installation is off from UTC
+    /// How many seconds the installation is off from UTC at the moment
     pub timezone_offset: Option<i32>,
     #[serde(default)]
     pub demo_mode: Option<bool>,
@@ -88,12 +89,322 @@ pub struct Installation {
     /// Installation view permissions for the requesting user.
     pub view_permissions: Option<ViewPermissions>,
     #[serde(default)]
-    /// A data attribute.
+    /// The most recent values of the installation's data attributes, and summaries combining several of them.
     ///
-    /// # Note
-    /// I'm not certain what exactly this is yet, but it's in the schema.
+    /// Only returned when the installation is requested with `extended` set. See [`Installation::attribute`].
     #[serde(deserialize_with = "deserialize_data_attribute")]
     pub extended: Option<Vec<Extended>>,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
+}
+
+impl Installation {
+    #[must_use]
+    /// Returns when the installation was created, in UTC. See [`crate::datetime`] for the supported date-time
+    /// types.
+    ///
+    /// [`Installation::timezone_offset`] is the offset of the installation's time zone as it is now, which is off
+    /// by an hour for moments on the other side of a daylight saving time change. To show the result in the
+    /// installation's own time zone, convert it using the name in [`Installation::timezone`], for example with
+    /// the `chrono-tz` crate.
+    pub fn created_at<T: FromTimestamp>(&self) -> Option<T> {
+        T::from_timestamp(self.sys_created, 0)
+    }
+
+    #[must_use]
+    /// Returns when data was most recently received, in UTC, or `None` if there is no data.
+    ///
+    /// See [`Installation::created_at`] for converting it to the installation's own time zone.
+    pub fn last_timestamp_at<T: FromTimestamp>(&self) -> Option<T> {
+        T::from_timestamp(self.last_timestamp?, 0)
+    }
+
+    #[must_use]
+    /// Returns the current local time of the installation, parsed from [`Installation::current_time`].
+    pub fn current_time_of_day<T: FromTimeOfDay>(&self) -> Option<T> {
+        let (hour, minute) = self.current_time.as_deref()?.split_once(':')?;
+
+        T::from_hm(hour.trim().parse().ok()?, minute.trim().parse().ok()?)
+    }
+
+    #[must_use]
+    /// Returns true if the requesting user may change the settings of this installation.
+    ///
+    /// This requires both an access level that allows writing, and view permissions that allow updating settings.
+    /// If VRM didn't return view permissions, this is false.
+    pub fn can_write_settings(&self) -> bool {
+        self.access_level.can_write()
+            && self
+                .view_permissions
+                .as_ref()
+                .is_some_and(|permissions| permissions.update_settings)
+    }
+
+    #[must_use]
+    /// Returns true if the requesting user may change values of this installation over MQTT.
+    ///
+    /// This requires being allowed to change settings, and view permissions that allow MQTT RPC on an installation
+    /// with two way communication. If VRM didn't return view permissions, this is false.
+    pub fn can_write_mqtt(&self) -> bool {
+        self.can_write_settings()
+            && self
+                .view_permissions
+                .as_ref()
+                .is_some_and(|permissions| permissions.mqtt_rpc && permissions.twoway)
+    }
+
+    #[must_use]
+    /// Returns true if the requesting user may view the settings of this installation.
+    pub fn can_read_settings(&self) -> bool {
+        self.view_permissions
+            .as_ref()
+            .is_none_or(|permissions| permissions.settings)
+    }
+
+    #[must_use]
+    /// Returns the extended data attribute or summary with the given code, such as [`codes::BATTERY_SOC`].
+    ///
+    /// This is only available if the installation was requested with `extended` set.
+    pub fn attribute(&self, code: &str) -> Option<&Extended> {
+        self.extended
+            .as_ref()?
+            .iter()
+            .find(|extended| extended.code() == code)
+    }
+
+    #[must_use]
+    /// Returns the value of the extended data attribute or summary with the given code.
+    pub fn attribute_value(&self, code: &str) -> Option<AttributeValue> {
+        self.attribute(code)?.value()
+    }
+
+    #[must_use]
+    /// Returns the battery state of charge, in percent.
+    pub fn battery_soc(&self) -> Option<f64> {
+        self.attribute_value(codes::BATTERY_SOC)?.as_f64()
+    }
+
+    #[must_use]
+    /// Returns the battery voltage, in volts.
+    pub fn battery_voltage(&self) -> Option<f64> {
+        self.attribute_value(codes::BATTERY_VOLTAGE)?.as_f64()
+    }
+
+    #[must_use]
+    /// Returns the power produced by DC-coupled PV, in watts.
+    pub fn pv_power(&self) -> Option<f64> {
+        self.attribute_value(codes::PV_POWER)?.as_f64()
+    }
+
+    #[must_use]
+    /// Returns the power used by AC loads, in watts.
+    pub fn consumption(&self) -> Option<f64> {
+        self.attribute_value(codes::CONSUMPTION)?.as_f64()
+    }
+
+    #[cfg(feature = "unknown-fields")]
+    #[must_use]
+    /// Returns the path of every field VRM sent for this installation that this library doesn't know about,
+    /// such as `tags[0].color` or `extended[3].dataAttributes[0].unit`.
+    ///
+    /// An empty list means the response matched the models exactly.
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        let mut push = |prefix: &str, extra: &serde_json::Map<String, Value>| {
+            fields.extend(extra.keys().map(|key| format!("{prefix}{key}")));
+        };
+
+        push("", &self.extra);
+
+        for (i, tag) in self.tags.iter().flatten().enumerate() {
+            push(&format!("tags[{i}]."), &tag.extra);
+        }
+
+        for (i, image) in self.images.iter().flatten().enumerate() {
+            push(&format!("images[{i}]."), &image.extra);
+        }
+
+        if let Some(permissions) = &self.view_permissions {
+            push("viewPermissions.", &permissions.extra);
+        }
+
+        for (i, extended) in self.extended.iter().flatten().enumerate() {
+            match extended {
+                Extended::Data(data) => {
+                    push(&format!("extended[{i}]."), &data.extra);
+
+                    for (j, value) in data.data_attribute_enum_values.iter().enumerate() {
+                        push(
+                            &format!("extended[{i}].dataAttributeEnumValues[{j}]."),
+                            &value.extra,
+                        );
+                    }
+                }
+                Extended::Summary(summary) => {
+                    push(&format!("extended[{i}]."), &summary.extra);
+
+                    for (j, attribute) in summary.data_attributes.iter().enumerate() {
+                        push(
+                            &format!("extended[{i}].dataAttributes[{j}]."),
+                            &attribute.extra,
+                        );
+                    }
+                }
+            }
+        }
+
+        fields
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "i32", into = "i32")]
+/// The access level a user has to an installation.
+pub enum AccessLevel {
+    /// 0: The user can only view the installation.
+    ReadOnly,
+    /// 1: The user has full control over the installation.
+    Full,
+    /// 2: The user can view and change settings, but not manage users.
+    Technician,
+    /// An access level this library doesn't know about yet.
+    Unknown(i32),
+}
+
+impl AccessLevel {
+    #[must_use]
+    /// Returns true if this access level allows changing settings.
+    pub const fn can_write(self) -> bool {
+        matches!(self, Self::Full | Self::Technician)
+    }
+}
+
+impl From<i32> for AccessLevel {
+    fn from(level: i32) -> Self {
+        match level {
+            0 => Self::ReadOnly,
+            1 => Self::Full,
+            2 => Self::Technician,
+            level => Self::Unknown(level),
+        }
+    }
+}
+
+impl From<AccessLevel> for i32 {
+    fn from(level: AccessLevel) -> Self {
+        match level {
+            AccessLevel::ReadOnly => 0,
+            AccessLevel::Full => 1,
+            AccessLevel::Technician => 2,
+            AccessLevel::Unknown(level) => level,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "i8", into = "i8")]
+/// Which notifications are sent out for an installation.
+pub enum AlarmMonitoring {
+    /// 0: Nothing is sent out.
+    Disabled,
+    /// 1: Only alarms are sent out.
+    AlarmsOnly,
+    /// 2: Both alarms and warnings are sent out.
+    AlarmsAndWarnings,
+    /// A value this library doesn't know about yet.
+    Unknown(i8),
+}
+
+impl From<i8> for AlarmMonitoring {
+    fn from(value: i8) -> Self {
+        match value {
+            0 => Self::Disabled,
+            1 => Self::AlarmsOnly,
+            2 => Self::AlarmsAndWarnings,
+            value => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<AlarmMonitoring> for i8 {
+    fn from(value: AlarmMonitoring) -> Self {
+        match value {
+            AlarmMonitoring::Disabled => 0,
+            AlarmMonitoring::AlarmsOnly => 1,
+            AlarmMonitoring::AlarmsAndWarnings => 2,
+            AlarmMonitoring::Unknown(value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "i8", into = "i8")]
+/// Whether an installation has something connected, such as mains or a generator.
+pub enum Presence {
+    /// 0: Not connected.
+    Absent,
+    /// 1: Connected.
+    Present,
+    /// 2: Detected automatically from the installation's data.
+    AutoDetect,
+    /// A value this library doesn't know about yet.
+    Unknown(i8),
+}
+
+impl Presence {
+    #[must_use]
+    /// Returns `Some(true)` or `Some(false)` if the presence is configured, or `None` if it isn't known.
+    pub const fn as_bool(self) -> Option<bool> {
+        match self {
+            Self::Absent => Some(false),
+            Self::Present => Some(true),
+            Self::AutoDetect | Self::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<i8> for Presence {
+    fn from(value: i8) -> Self {
+        match value {
+            0 => Self::Absent,
+            1 => Self::Present,
+            2 => Self::AutoDetect,
+            value => Self::Unknown(value),
+        }
+    }
+}
+
+impl From<Presence> for i8 {
+    fn from(value: Presence) -> Self {
+        match value {
+            Presence::Absent => 0,
+            Presence::Present => 1,
+            Presence::AutoDetect => 2,
+            Presence::Unknown(value) => value,
+        }
+    }
+}
+
+/// (De)serializes a `bool` that VRM sends as `0` or `1`.
+pub(crate) mod int_bool {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<bool, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(i8::deserialize(deserializer)? != 0)
+    }
+
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    pub fn serialize<S>(value: &bool, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i8(i8::from(*value))
+    }
 }
 
 fn deserialize_data_attribute<'de, D>(deserializer: D) -> Result<Option<Vec<Extended>>, D::Error>
@@ -107,12 +418,25 @@ where
         |array| {
             let extended = array
                 .iter()
-                .flat_map(|v| {
-                    if v.get("code").is_some() {
+                .filter_map(|v| {
+                    // Both kinds have a `code`, but only summaries list the data attributes they combine.
+                    let result = if v.get("dataAttributes").is_some() {
                         serde_json::from_value(v.clone()).map(Extended::Summary)
                     } else {
                         serde_json::from_value(v.clone()).map(Extended::Data)
-                    }
+                    };
+
+                    // One unexpected entry shouldn't fail the whole installation, but it shouldn't go unnoticed either.
+                    result
+                        .inspect_err(|error| {
+                            let code = v.get("code").and_then(|code| code.as_str());
+                            tracing::warn!(
+                                code,
+                                %error,
+                                "dropping extended entry that could not be parsed"
+                            );
+                        })
+                        .ok()
                 })
                 .collect();
 
@@ -121,7 +445,8 @@ where
     )
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
 pub enum Extended {
     Data(Data),
     Summary(Summary),
@@ -157,12 +482,101 @@ impl Extended {
             Self::Data(_) => None,
         }
     }
+
+    #[must_use]
+    /// Returns the code of the data attribute or summary, such as `bs` for the battery state of charge.
+    pub fn code(&self) -> &str {
+        match self {
+            Self::Data(data) => &data.code,
+            Self::Summary(summary) => &summary.code,
+        }
+    }
+
+    #[must_use]
+    /// Returns the device instance of a data attribute, to tell apart devices of the same type. Summaries combine
+    /// several devices, so they have none.
+    pub fn instance(&self) -> Option<&str> {
+        match self {
+            Self::Data(data) => Some(&data.instance),
+            Self::Summary(_) => None,
+        }
+    }
+
+    #[must_use]
+    /// Returns the value, typed according to its data type. See [`Data::value`] and [`Summary::value`].
+    pub fn value(&self) -> Option<AttributeValue> {
+        match self {
+            Self::Data(data) => data.value(),
+            Self::Summary(summary) => summary.value(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The value of an extended data attribute or summary.
+pub enum AttributeValue {
+    /// A number, such as a voltage or a state of charge.
+    Numeric(f64),
+    /// One of a fixed set of values, such as the state of a charger.
+    Enum {
+        /// The raw value.
+        value: i32,
+        /// The name VRM gives this value, if it sent one.
+        name: Option<String>,
+    },
+    /// Free text, such as a firmware version.
+    Text(String),
+}
+
+impl AttributeValue {
+    #[must_use]
+    /// Returns the number if this is a numeric value.
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Numeric(value) => Some(*value),
+            Self::Enum { .. } | Self::Text(_) => None,
+        }
+    }
+
+    #[must_use]
+    /// Returns the raw value and its name if this is an enum value.
+    pub fn as_enum(&self) -> Option<(i32, Option<&str>)> {
+        match self {
+            Self::Enum { value, name } => Some((*value, name.as_deref())),
+            Self::Numeric(_) | Self::Text(_) => None,
+        }
+    }
+
+    #[must_use]
+    /// Returns the text if this is a text value.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) => Some(text),
+            Self::Numeric(_) | Self::Enum { .. } => None,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// Codes of commonly used extended data attributes and summaries, for use with [`Installation::attribute`].
+pub mod codes {
+    /// The battery state of charge, in percent.
+    pub const BATTERY_SOC: &str = "bs";
+    /// The battery voltage, in volts.
+    pub const BATTERY_VOLTAGE: &str = "bv";
+    /// The battery current, in amps.
+    pub const BATTERY_CURRENT: &str = "bc";
+    /// The state of the system, such as bulk or float charging.
+    pub const SYSTEM_STATE: &str = "ss";
+    /// The power produced by DC-coupled PV, in watts.
+    pub const PV_POWER: &str = "Pdc";
+    /// The power used by AC loads, in watts.
+    pub const CONSUMPTION: &str = "consumption";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Data {
     #[serde(rename = "idDataAttribute")]
-    pub data_id: i32,
+    pub data_id: DataAttributeId,
     pub code: String,
     pub description: String,
     #[serde(rename = "formatWithUnit")]
@@ -182,21 +596,70 @@ pub struct Data {
     #[serde(rename = "formattedValue")]
     pub formatted_value: String,
     #[serde(rename = "formattedValueWithUnit")]
+    pub formatted_value_with_unit: String,
+    #[serde(rename = "dataAttributeEnumValues", default)]
+    /// The names of the values an `enum` attribute can have.
     pub data_attribute_enum_values: Vec<DataAttributeEnumValue>,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Data {
+    #[must_use]
+    /// Returns when this value was logged, in a time zone `offset_secs` seconds from UTC.
+    ///
+    /// Pass 0 for UTC. The [`Installation::timezone_offset`] of the installation this value belongs to gives its
+    /// own time zone, but only while the daylight saving time is the same as when the value was logged.
+    pub fn timestamp_at<T: FromTimestamp>(&self, offset_secs: i32) -> Option<T> {
+        T::from_timestamp(self.timestamp.trim().parse().ok()?, offset_secs)
+    }
+
+    #[must_use]
+    /// Returns the value, parsed according to [`Data::data_type`].
+    ///
+    /// Numbers are read from the raw value, enums are named using [`Data::data_attribute_enum_values`], and
+    /// anything else is returned as text. Returns `None` if there is no value.
+    pub fn value(&self) -> Option<AttributeValue> {
+        let raw = self.raw_value.trim();
+
+        match self.data_type.as_str() {
+            "float" | "int" | "integer" | "number" => raw.parse().ok().map(AttributeValue::Numeric),
+            "enum" => {
+                let value = raw.parse().ok()?;
+                let name = self
+                    .data_attribute_enum_values
+                    .iter()
+                    .find(|enum_value| enum_value.value_enum == value)
+                    .map(|enum_value| enum_value.name_enum.clone())
+                    .or_else(|| Some(self.text_value.clone()).filter(|text| !text.is_empty()));
+
+                Some(AttributeValue::Enum { value, name })
+            }
+            _ if !self.text_value.is_empty() => Some(AttributeValue::Text(self.text_value.clone())),
+            _ if !raw.is_empty() => Some(AttributeValue::Text(raw.to_string())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataAttributeEnumValue {
     #[serde(rename = "nameEnum")]
     pub name_enum: String,
     #[serde(rename = "valueEnum")]
     pub value_enum: i32,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Summary {
     #[serde(rename = "idDataAttribute")]
-    pub data_id: i32,
+    pub data_id: DataAttributeId,
     pub code: String,
     pub description: String,
     #[serde(rename = "rawValue")]
@@ -209,35 +672,64 @@ pub struct Summary {
     pub format_with_unit: String,
     #[serde(rename = "dataAttributes")]
     pub data_attributes: Vec<DataAttribute>,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Summary {
+    #[must_use]
+    /// Returns the value, as a number if VRM sent one, otherwise as text.
+    pub fn value(&self) -> Option<AttributeValue> {
+        self.raw_value.map(AttributeValue::Numeric).or_else(|| {
+            self.text_value
+                .clone()
+                .filter(|text| !text.is_empty())
+                .map(AttributeValue::Text)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DataAttribute {
     pub instance: i32,
     #[serde(rename = "dbusServiceType")]
     pub dbus_service_type: String,
     #[serde(rename = "dbusPath")]
     pub dbus_path: String,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     #[serde(rename = "idTag")]
-    pub tag_id: i32,
+    pub tag_id: TagId,
     pub name: String,
     pub automatic: bool,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Image {
     #[serde(rename = "idSiteImage")]
     pub image_id: i32,
     #[serde(rename = "imageName")]
     pub name: String,
     pub url: String,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(clippy::struct_excessive_bools)]
 /// Installation view permissions for the requesting user.
 pub struct ViewPermissions {
@@ -263,6 +755,216 @@ pub struct ViewPermissions {
     pub nodered: bool,
     /// True if the installation has a Node RED Dashboard.
     pub nodered_dash: bool,
-    /// True if the installation has SignalK.
+    /// True if the installation has `SignalK`.
     pub signalk: bool,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn fixture() -> Vec<Value> {
+        serde_json::from_str(include_str!("mock/installations.json")).expect("valid fixture")
+    }
+
+    fn round_trip(json: &Value) -> Value {
+        let installation: Installation =
+            serde_json::from_value(json.clone()).expect("valid installation");
+
+        serde_json::to_value(installation).expect("serializable installation")
+    }
+
+    /// Returns the first installation of the fixture, with fields added that the models don't have.
+    fn with_unknown_fields() -> Value {
+        let mut installation = fixture().swap_remove(0);
+        installation["solarForecast"] = json!({ "enabled": true });
+        installation["extended"][0]["precision"] = json!(1);
+
+        installation
+    }
+
+    /// Returns the data attribute at `index` of the first installation of the fixture, after `edit`.
+    fn data(index: usize, edit: impl FnOnce(&mut Value)) -> Data {
+        let mut data = fixture().swap_remove(0)["extended"][index].take();
+        edit(&mut data);
+
+        serde_json::from_value(data).expect("valid data attribute")
+    }
+
+    fn enum_value(value: i32, name: Option<&str>) -> AttributeValue {
+        AttributeValue::Enum {
+            value,
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn numeric_values() {
+        assert_eq!(data(0, |_| {}).value(), Some(AttributeValue::Numeric(87.5)));
+
+        let count = data(0, |data| {
+            data["dataType"] = json!("int");
+            data["rawValue"] = json!(" 12 ");
+        });
+        assert_eq!(count.value(), Some(AttributeValue::Numeric(12.0)));
+
+        let missing = data(0, |data| data["rawValue"] = json!(""));
+        assert_eq!(missing.value(), None);
+    }
+
+    #[test]
+    fn enum_values_are_named() {
+        assert_eq!(data(2, |_| {}).value(), Some(enum_value(3, Some("Bulk"))));
+
+        let unknown = data(2, |data| {
+            data["rawValue"] = json!("9");
+            data["textValue"] = json!("");
+        });
+        assert_eq!(unknown.value(), Some(enum_value(9, None)));
+
+        let without_names = data(2, |data| {
+            data["dataAttributeEnumValues"] = json!([]);
+            data["textValue"] = json!("Float");
+            data["rawValue"] = json!("5");
+        });
+        assert_eq!(without_names.value(), Some(enum_value(5, Some("Float"))));
+
+        let without_any_name = data(2, |data| {
+            data.as_object_mut()
+                .expect("data object")
+                .remove("dataAttributeEnumValues");
+            data["textValue"] = json!("");
+        });
+        assert_eq!(without_any_name.value(), Some(enum_value(3, None)));
+    }
+
+    #[test]
+    fn text_values() {
+        let version = data(0, |data| {
+            data["dataType"] = json!("string");
+            data["textValue"] = json!("v3.20");
+        });
+        assert_eq!(
+            version.value(),
+            Some(AttributeValue::Text("v3.20".to_string()))
+        );
+
+        let raw = data(0, |data| data["dataType"] = json!("string"));
+        assert_eq!(raw.value(), Some(AttributeValue::Text("87.5".to_string())));
+
+        let empty = data(0, |data| {
+            data["dataType"] = json!("string");
+            data["rawValue"] = json!("");
+        });
+        assert_eq!(empty.value(), None);
+    }
+
+    #[test]
+    fn formatted_value_with_unit() {
+        let soc = data(0, |_| {});
+        assert_eq!(soc.formatted_value, "87.5");
+        assert_eq!(soc.formatted_value_with_unit, "87.5 %");
+
+        let json = serde_json::to_value(&soc).expect("serializable data attribute");
+        assert_eq!(json["formattedValueWithUnit"], "87.5 %");
+    }
+
+    #[test]
+    fn typed_accessors() {
+        let mut installations = fixture();
+        let installation: Installation =
+            serde_json::from_value(installations.swap_remove(0)).expect("valid installation");
+
+        assert_eq!(installation.battery_soc(), Some(87.5));
+        assert_eq!(installation.battery_voltage(), Some(53.21));
+        assert_eq!(installation.pv_power(), Some(1250.0));
+        assert_eq!(installation.consumption(), Some(412.0));
+        assert_eq!(
+            installation
+                .attribute_value(codes::SYSTEM_STATE)
+                .and_then(|value| value.as_enum().map(|(value, _)| value)),
+            Some(3)
+        );
+
+        let without_extended: Installation =
+            serde_json::from_value(installations.swap_remove(0)).expect("valid installation");
+        assert_eq!(without_extended.battery_soc(), None);
+        assert_eq!(without_extended.consumption(), None);
+    }
+
+    #[test]
+    fn round_trips_the_fixture() {
+        for installation in fixture() {
+            assert_eq!(round_trip(&installation), installation);
+        }
+    }
+
+    #[cfg(feature = "unknown-fields")]
+    #[test]
+    fn keeps_unknown_fields() {
+        let installation = with_unknown_fields();
+        assert_eq!(round_trip(&installation), installation);
+
+        let installation: Installation =
+            serde_json::from_value(installation).expect("valid installation");
+        assert_eq!(
+            installation.unknown_fields(),
+            ["solarForecast", "extended[0].precision"]
+        );
+    }
+
+    #[cfg(not(feature = "unknown-fields"))]
+    #[test]
+    fn drops_unknown_fields() {
+        let mut expected = with_unknown_fields();
+        expected
+            .as_object_mut()
+            .expect("installation object")
+            .remove("solarForecast");
+        expected["extended"][0]
+            .as_object_mut()
+            .expect("data object")
+            .remove("precision");
+
+        assert_eq!(round_trip(&with_unknown_fields()), expected);
+    }
+
+    #[test]
+    fn timestamps_are_in_utc() {
+        let installation: Installation =
+            serde_json::from_value(with_unknown_fields()).expect("valid installation");
+        assert_eq!(installation.timezone_offset, Some(7200));
+
+        let created_at: std::time::SystemTime = installation.created_at().expect("creation time");
+        assert_eq!(
+            crate::datetime::IntoTimestamp::into_timestamp(created_at),
+            installation.sys_created
+        );
+
+        #[cfg(feature = "chrono")]
+        {
+            let last: chrono::DateTime<chrono::FixedOffset> =
+                installation.last_timestamp_at().expect("last timestamp");
+            assert_eq!(last.offset().local_minus_utc(), 0);
+            assert_eq!(last.timestamp(), 1_718_791_200);
+        }
+
+        let without_offset = Installation {
+            timezone_offset: None,
+            last_timestamp: None,
+            ..installation
+        };
+        assert!(without_offset
+            .created_at::<std::time::SystemTime>()
+            .is_some());
+        assert!(without_offset
+            .last_timestamp_at::<std::time::SystemTime>()
+            .is_none());
+    }
 }
@@ -1,90 +1,247 @@
 #![warn(clippy::pedantic, clippy::nursery)]
 #![deny(clippy::unwrap_used)]
 
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use reqwest::Client;
-use serde::Deserialize;
+use async_lock::{Mutex, RwLock};
+pub use http::Method;
+use http::StatusCode;
 use serde_json::Value;
-use tokio::sync::RwLock;
 
+pub mod alarms;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub mod blocking;
+mod builder;
+#[cfg(feature = "cassette")]
+pub mod cassette;
+pub mod credentials;
+pub mod datetime;
+pub mod diagnostics;
+mod endpoint;
+mod error;
+pub mod fleet;
+pub mod ids;
 pub mod installations;
 pub mod login;
+#[cfg(feature = "mock")]
+pub mod mock;
+#[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+pub mod mqtt;
+pub mod query;
+pub mod rate_limit;
+mod runtime;
+pub mod session;
+pub mod stats;
+#[cfg(all(
+    feature = "tokio",
+    any(feature = "rustls", feature = "native-tls", feature = "mqtt"),
+    not(target_arch = "wasm32")
+))]
+pub mod tls;
+pub mod transport;
 pub mod users;
+pub mod watch;
+
+pub use builder::VictronBuilder;
+use credentials::Credentials;
+pub use error::{Error, ErrorCode, Failure};
+use ids::UserId;
+use query::Query;
+use rate_limit::{RateLimit, RateLimiter};
+use transport::{build_request, DefaultTransport, HttpResponse, HttpTransport};
 
 const BASE_URL: &str = "https://vrmapi.victronenergy.com/v2";
 
-#[derive(Clone)]
-pub struct Victron {
-    /// A [`Client`] used to send requests.
-    client: Client,
+#[derive(Debug, Clone)]
+pub struct Victron<T = DefaultTransport> {
+    /// The [`HttpTransport`] used to send requests.
+    transport: T,
+    /// The base URL of the VRM API, without a trailing slash.
+    base_url: Arc<str>,
 
     /// The token used to authenticate requests.
     /// This is either a Bearer token or an Access token, depending on the endpoint.
-    token: Token,
+    ///
+    /// It is shared between all clones, so a token refreshed by one clone is used by all of them.
+    token: Arc<RwLock<Token>>,
+    /// Held while logging in again after the token was rejected, so clones that see the same rejection only log in
+    /// once. The token itself is only locked to replace it, so requests aren't blocked by a slow login.
+    refreshing: Arc<Mutex<()>>,
     /// The user id of the currently logged in user.
     ///
     /// This is fetched when needed and stored in a [`RefCell`] to allow for mutable access.
     /// The [`RefCell`] is ideally updated only once, when any function that needs the user id is called.
-    user_id: Arc<RwLock<Option<i32>>>,
+    user_id: Arc<RwLock<Option<UserId>>>,
+    /// An optional rate limiter, shared between all clones of this client.
+    rate_limiter: Option<Arc<RateLimiter>>,
+    /// Credentials used to log in again when the token is rejected.
+    credentials: Option<Arc<Credentials>>,
+    /// The TLS options of the builder, for MQTT connections.
+    #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+    tls: Option<tls::TlsConfig>,
 }
 
-impl Victron {
+impl<T: HttpTransport> Victron<T> {
+    pub(crate) fn new(builder: VictronBuilder<T>, token: Token, user_id: Option<UserId>) -> Self {
+        Self {
+            transport: builder.transport,
+            base_url: builder.base_url,
+            token: Arc::new(RwLock::new(token)),
+            refreshing: Arc::default(),
+            user_id: Arc::new(RwLock::new(user_id)),
+            rate_limiter: None,
+            credentials: None,
+            #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+            tls: builder.tls,
+        }
+    }
+
+    #[must_use]
+    /// Limits how many requests this client, and every clone made from it afterwards, may send.
+    ///
+    /// Requests that would exceed the limit wait until the bucket has refilled instead of failing.
+    /// Use [`RateLimit::VRM`] to stay within the budget documented by VRM.
+    ///
+    /// # Panics
+    /// If the refill rate of `limit` is not a positive number, see [`RateLimit::new`].
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(limit)));
+        self
+    }
+
+    #[must_use]
+    /// Sets the credentials used to transparently log in again when VRM rejects the current token,
+    /// for example because a bearer token has expired.
+    ///
+    /// The new token is shared with every clone of this client, and the rejected request is retried once. Logging in
+    /// again counts towards the rate limit, if there is one.
+    pub fn with_credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(Arc::new(credentials));
+        self
+    }
+
+    /// Sends an authenticated request to `url`.
+    ///
+    /// If the token is rejected and credentials are available, a new token is obtained and the request is
+    /// sent once more.
+    pub(crate) async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        query: &Query,
+        body: Option<&Value>,
+    ) -> Result<HttpResponse, Error> {
+        let mut reauthenticated = false;
+
+        loop {
+            self.throttle().await;
+
+            let token = self.token.read().await.clone();
+            let mut request = build_request(method.clone(), url, query, body)?;
+            request
+                .headers_mut()
+                .insert("x-authorization", token.to_string().try_into()?);
+
+            let resp = self.transport.send(request).await?;
+
+            if resp.status() == StatusCode::UNAUTHORIZED
+                && !reauthenticated
+                && self.reauthenticate(&token).await?
+            {
+                reauthenticated = true;
+                continue;
+            }
+
+            return Ok(resp);
+        }
+    }
+
+    /// Replaces `rejected` with a fresh token, returning whether there is a new token to retry with.
+    async fn reauthenticate(&self, rejected: &Token) -> Result<bool, Error> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(false);
+        };
+
+        // A request sent while logging in, such as by a callback using this client, can't wait for the login.
+        let client = Arc::as_ptr(&self.refreshing).addr();
+        if credentials::is_refreshing(client) {
+            return Ok(false);
+        }
+
+        let _refreshing = self.refreshing.lock().await;
+
+        // Another clone may have already replaced the token while we were waiting for the lock.
+        if *self.token.read().await != *rejected {
+            return Ok(true);
+        }
+
+        tracing::debug!("token was rejected, logging in again");
+        self.throttle().await;
+        let token =
+            credentials::Refreshing::new(client, credentials.authenticate(&self.builder())).await?;
+        *self.token.write().await = token;
+
+        Ok(true)
+    }
+
+    /// Returns a builder with the same transport and base URL as this client.
+    pub(crate) fn builder(&self) -> VictronBuilder<T> {
+        VictronBuilder {
+            transport: self.transport.clone(),
+            base_url: Arc::clone(&self.base_url),
+            #[cfg(all(feature = "mqtt", not(target_arch = "wasm32")))]
+            tls: self.tls.clone(),
+        }
+    }
+
+    /// Waits for the rate limiter, if there is one, to allow another request.
+    pub(crate) async fn throttle(&self) {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+    }
+
     /// Gets the user id of the currently logged in user, fetching it if it hasn't been fetched yet.
     ///
     /// # Errors
     /// - [`Error::Reqwest`] if there was an error sending the request.
     /// - [`Error::Victron`] if the request failed.
-    /// - [`Error::ParseInt`] if the user id could not be parsed as an integer.
-    pub async fn ensure_user_id(&self) -> Result<i32, Error> {
+    pub async fn ensure_user_id(&self) -> Result<UserId, Error> {
         if let Some(user_id) = self.user_id.read().await.as_ref() {
             return Ok(*user_id);
         }
 
         let user = self.get_user_info().await?;
 
-        self.user_id.write().await.replace(user.id.parse()?);
+        self.user_id.write().await.replace(user.id);
 
-        Ok(user.id.parse()?)
+        Ok(user.id)
     }
 }
 
-#[derive(Clone)]
-pub(crate) enum Token {
+#[derive(Clone, PartialEq, Eq)]
+/// A token used to authenticate requests.
+pub enum Token {
+    /// A bearer token, as returned by [`Victron::login`].
     Bearer(String),
+    /// An access token, as created in the VRM portal.
     Access(String),
 }
 
-impl ToString for Token {
-    fn to_string(&self) -> String {
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Bearer(token) => format!("Bearer {token}"),
-            Self::Access(token) => format!("Token {token}"),
+            Self::Bearer(_) => f.write_str("Bearer(..)"),
+            Self::Access(_) => f.write_str("Access(..)"),
         }
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-pub enum Error {
-    #[error("Reqwest Error: {0}")]
-    Reqwest(#[from] reqwest::Error),
-
-    #[error("Failed to parse integer: {0}")]
-    ParseInt(#[from] std::num::ParseIntError),
-
-    #[error("Victron Error: {0:?}")]
-    Victron(Failure),
-}
-
-#[derive(Debug, Clone, Deserialize)]
-pub struct Failure {
-    pub success: bool,
-    pub errors: Value,
-    pub error_code: Option<String>,
-}
-
-impl From<Failure> for Error {
-    fn from(failure: Failure) -> Self {
-        Self::Victron(failure)
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bearer(token) => write!(f, "Bearer {token}"),
+            Self::Access(token) => write!(f, "Token {token}"),
+        }
     }
 }
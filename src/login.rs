@@ -1,68 +1,177 @@
-use std::{string::ToString, sync::Arc};
+use std::{fmt, string::ToString};
 
-use reqwest::Client;
-use serde::Deserialize;
-use serde_json::json;
-use tokio::sync::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::{Error, Failure, Token, Victron, BASE_URL};
+use crate::{
+    ids::UserId,
+    query::Query,
+    transport::{build_request, parse_response, DefaultTransport, HttpTransport},
+    Error, ErrorCode, Failure, Method, Token, Victron, VictronBuilder,
+};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Success {
     pub token: Option<String>,
     #[serde(rename = "idUser")]
-    pub user_id: i32,
+    pub user_id: UserId,
     pub verification_mode: String,
     pub verification_sent: bool,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Success {
+    #[must_use]
+    /// Returns the second factor VRM is waiting for, if the login still needs to be verified.
+    pub fn pending_verification(&self) -> Option<VerificationMode> {
+        if self.token.is_some() {
+            return None;
+        }
+
+        match VerificationMode::from(self.verification_mode.as_str()) {
+            VerificationMode::Password => None,
+            mode => Some(mode),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// How a login is verified.
+pub enum VerificationMode {
+    /// Only the password is needed, there is no second factor.
+    Password,
+    /// A code is sent to the user's phone by SMS.
+    Sms,
+    /// A code is generated by the user's authenticator app.
+    Totp,
+    /// A verification mode this library doesn't know about yet.
+    Other(String),
+}
+
+impl From<&str> for VerificationMode {
+    fn from(mode: &str) -> Self {
+        match mode {
+            "password" => Self::Password,
+            "sms" => Self::Sms,
+            "totp" => Self::Totp,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+/// The result of [`Victron::begin_login`].
+#[derive(Debug)]
+pub enum LoginOutcome<T = DefaultTransport> {
+    /// The credentials were accepted and no second factor is needed.
+    LoggedIn(Victron<T>),
+    /// The credentials were accepted, but a verification code has to be submitted with
+    /// [`PendingVerification::verify`] to finish logging in.
+    VerificationRequired(PendingVerification<T>),
+}
+
+/// A login that is waiting for a two-factor verification code.
+pub struct PendingVerification<T = DefaultTransport> {
+    builder: VictronBuilder<T>,
+    username: String,
+    password: String,
+    remember_me: bool,
+    mode: VerificationMode,
+    sent: bool,
+}
+
+impl<T: HttpTransport> PendingVerification<T> {
+    #[must_use]
+    /// How the verification code is delivered to the user.
+    pub const fn mode(&self) -> &VerificationMode {
+        &self.mode
+    }
+
+    #[must_use]
+    /// True if VRM has sent the verification code, for example by SMS.
+    pub const fn code_sent(&self) -> bool {
+        self.sent
+    }
+
+    /// Submits the SMS or TOTP verification code to finish logging in.
+    ///
+    /// If the code is wrong, this can be called again with another code.
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::InvalidVerificationCode` if VRM did not accept the code.
+    pub async fn verify(&self, code: &str) -> Result<Victron<T>, Error> {
+        let result = self
+            .builder
+            .request_login(&self.username, &self.password, Some(code), self.remember_me)
+            .await;
+
+        match result {
+            Ok((token, user_id)) => Ok(self.builder.clone().with_token(token, Some(user_id))),
+            Err(
+                Error::Victron(Failure {
+                    code: ErrorCode::InvalidCredentials | ErrorCode::TwoFactorRequired,
+                    ..
+                })
+                | Error::VerificationRequired(_),
+            ) => Err(Error::InvalidVerificationCode),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<T> fmt::Debug for PendingVerification<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingVerification")
+            .field("username", &self.username)
+            .field("remember_me", &self.remember_me)
+            .field("mode", &self.mode)
+            .field("sent", &self.sent)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DemoSuccess {
     pub token: Option<String>,
 }
 
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
 impl Victron {
     /// Logs into the Victron API.
     ///
+    /// For accounts with two-factor authentication, `sms_token` has to contain the verification code.
+    /// See [`Victron::begin_login`] for a flow that asks VRM whether a code is needed first.
+    ///
     /// # Errors
     /// - `Error::Reqwest` if there was an error sending the request.
     /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    /// - `Error::VerificationRequired` if a verification code is needed, but `sms_token` was not given.
     pub async fn login(
         username: &str,
         password: &str,
         sms_token: Option<&str>,
         remember_me: bool,
     ) -> Result<Self, Error> {
-        let client = Client::new();
-
-        let resp = client
-            .post(format!("{BASE_URL}/auth/login"))
-            .json(&json!({
-                "username": username,
-                "password": password,
-                "sms_token": sms_token.map(ToString::to_string),
-                "remember_me": remember_me,
-            }))
-            .send()
-            .await?;
-
-        if resp.status().is_success() {
-            let success = resp.json::<Success>().await?;
-
-            return Ok(Self {
-                client,
-                token: Token::Bearer(success.token.ok_or(Error::Victron(Failure {
-                    error_code: Some("no_token".to_string()),
-                    errors: json!("No token returned"),
-                    success: false,
-                }))?),
-                user_id: Arc::new(RwLock::new(Some(success.user_id))),
-            });
-        }
-
-        let failure = resp.json::<Failure>().await?;
+        VictronBuilder::new()
+            .login(username, password, sms_token, remember_me)
+            .await
+    }
 
-        Err(failure.into())
+    /// Starts logging into the Victron API, for accounts that may have two-factor authentication enabled.
+    ///
+    /// If VRM needs a verification code, this returns [`LoginOutcome::VerificationRequired`], and the login is
+    /// finished with [`PendingVerification::verify`].
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub async fn begin_login(
+        username: &str,
+        password: &str,
+        remember_me: bool,
+    ) -> Result<LoginOutcome, Error> {
+        VictronBuilder::new()
+            .begin_login(username, password, remember_me)
+            .await
     }
 
     /// Logs into the Victron API with an access token.
@@ -71,67 +180,197 @@ impl Victron {
     /// - `Error::Reqwest` if there was an error sending the request.
     /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
     pub async fn login_access_token(username: &str, access_token: &str) -> Result<Self, Error> {
-        let client = Client::new();
+        VictronBuilder::new()
+            .login_access_token(username, access_token)
+            .await
+    }
 
-        let resp = client
-            .post(format!("{BASE_URL}/auth/login"))
-            .json(&json!({
-                "username": username,
-                "password": access_token,
-                "remember_me": true,
-            }))
-            .send()
+    /// Logs into the Victron API as a demo user.
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub async fn login_as_demo() -> Result<Self, Error> {
+        VictronBuilder::new().login_as_demo().await
+    }
+}
+
+impl<T: HttpTransport> VictronBuilder<T> {
+    /// Logs into the Victron API. See [`Victron::login`].
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    /// - `Error::VerificationRequired` if a verification code is needed, but `sms_token` was not given.
+    pub async fn login(
+        self,
+        username: &str,
+        password: &str,
+        sms_token: Option<&str>,
+        remember_me: bool,
+    ) -> Result<Victron<T>, Error> {
+        let (token, user_id) = self
+            .request_login(username, password, sms_token, remember_me)
             .await?;
 
-        if resp.status().is_success() {
-            let success = resp.json::<Success>().await?;
-
-            return Ok(Self {
-                client,
-                token: Token::Access(success.token.ok_or(Error::Victron(Failure {
-                    error_code: Some("no_token".to_string()),
-                    errors: json!("No token returned"),
-                    success: false,
-                }))?),
-                user_id: Arc::new(RwLock::new(Some(success.user_id))),
-            });
+        Ok(self.with_token(token, Some(user_id)))
+    }
+
+    /// Starts logging into the Victron API. See [`Victron::begin_login`].
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub async fn begin_login(
+        self,
+        username: &str,
+        password: &str,
+        remember_me: bool,
+    ) -> Result<LoginOutcome<T>, Error> {
+        let success = self
+            .send_password_login(username, password, None, remember_me)
+            .await?;
+
+        if let Some(mode) = success.pending_verification() {
+            return Ok(LoginOutcome::VerificationRequired(PendingVerification {
+                builder: self,
+                username: username.to_string(),
+                password: password.to_string(),
+                remember_me,
+                mode,
+                sent: success.verification_sent,
+            }));
         }
 
-        let failure = resp.json::<Failure>().await?;
+        let token = Token::Bearer(success.token.ok_or_else(no_token)?);
 
-        Err(failure.into())
+        Ok(LoginOutcome::LoggedIn(
+            self.with_token(token, Some(success.user_id)),
+        ))
     }
 
-    /// Logs into the Victron API as a demo user.
+    /// Logs into the Victron API with an access token. See [`Victron::login_access_token`].
     ///
     /// # Errors
     /// - `Error::Reqwest` if there was an error sending the request.
     /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
-    pub async fn login_as_demo() -> Result<Self, Error> {
-        let client = Client::new();
+    pub async fn login_access_token(
+        self,
+        username: &str,
+        access_token: &str,
+    ) -> Result<Victron<T>, Error> {
+        let (token, user_id) = self
+            .request_access_token_login(username, access_token)
+            .await?;
+
+        Ok(self.with_token(token, Some(user_id)))
+    }
 
-        let resp = client
-            .post(format!("{BASE_URL}/auth/loginAsDemo"))
-            .header("content-type", "application/json")
-            .send()
+    /// Logs into the Victron API as a demo user. See [`Victron::login_as_demo`].
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if there was an error sending the request.
+    /// - `Error::Victron` if the login failed, for example due to incorrect credentials.
+    pub async fn login_as_demo(self) -> Result<Victron<T>, Error> {
+        let request = build_request(
+            Method::POST,
+            &format!("{}/auth/loginAsDemo", self.base_url),
+            &Query::new(),
+            None,
+        )?;
+
+        let resp = self.transport.send(request).await?;
+        let demo_success = parse_response::<DemoSuccess>(&resp)?;
+
+        let token = Token::Bearer(demo_success.token.ok_or_else(no_token)?);
+
+        Ok(self.with_token(token, None))
+    }
+
+    /// Sends a username and password login request, returning the bearer token and user id.
+    pub(crate) async fn request_login(
+        &self,
+        username: &str,
+        password: &str,
+        sms_token: Option<&str>,
+        remember_me: bool,
+    ) -> Result<(Token, UserId), Error> {
+        let success = self
+            .send_password_login(username, password, sms_token, remember_me)
             .await?;
 
-        if resp.status().is_success() {
-            let demo_success = resp.json::<DemoSuccess>().await?;
-
-            return Ok(Self {
-                client,
-                token: Token::Bearer(demo_success.token.ok_or(Error::Victron(Failure {
-                    error_code: Some("no_token".to_string()),
-                    errors: json!("No token returned"),
-                    success: false,
-                }))?),
-                user_id: Arc::default(),
-            });
+        if let Some(mode) = success.pending_verification() {
+            return Err(Error::VerificationRequired(mode));
         }
 
-        let failure = resp.json::<Failure>().await?;
+        Ok((
+            Token::Bearer(success.token.ok_or_else(no_token)?),
+            success.user_id,
+        ))
+    }
+
+    async fn send_password_login(
+        &self,
+        username: &str,
+        password: &str,
+        sms_token: Option<&str>,
+        remember_me: bool,
+    ) -> Result<Success, Error> {
+        self.send_login(&json!({
+            "username": username,
+            "password": password,
+            "sms_token": sms_token.map(ToString::to_string),
+            "remember_me": remember_me,
+        }))
+        .await
+    }
+
+    /// Sends an access token login request, returning the access token and user id.
+    pub(crate) async fn request_access_token_login(
+        &self,
+        username: &str,
+        access_token: &str,
+    ) -> Result<(Token, UserId), Error> {
+        let success = self
+            .send_login(&json!({
+                "username": username,
+                "password": access_token,
+                "remember_me": true,
+            }))
+            .await?;
 
-        Err(failure.into())
+        Ok((
+            Token::Access(success.token.ok_or_else(no_token)?),
+            success.user_id,
+        ))
     }
+
+    async fn send_login(&self, body: &Value) -> Result<Success, Error> {
+        let request = build_request(
+            Method::POST,
+            &format!("{}/auth/login", self.base_url),
+            &Query::new(),
+            Some(body),
+        )?;
+
+        let resp = self.transport.send(request).await?;
+
+        match parse_response(&resp) {
+            // Without a token, an unauthorized login means the credentials themselves were rejected.
+            Err(Error::Victron(mut failure)) if failure.code == ErrorCode::TokenExpired => {
+                failure.code = ErrorCode::InvalidCredentials;
+                Err(failure.into())
+            }
+            result => result,
+        }
+    }
+}
+
+fn no_token() -> Error {
+    Error::Victron(Failure {
+        status: 200,
+        code: ErrorCode::Unknown("no_token".to_string()),
+        errors: json!("No token returned"),
+        body: None,
+    })
 }
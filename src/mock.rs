@@ -0,0 +1,443 @@
+//! An in-process stand-in for the VRM API, for testing code that uses this library.
+//!
+//! [`MockVrm`] is an [`HttpTransport`] that answers requests itself instead of sending them over the network. It
+//! serves realistic fixtures for every endpoint this library supports, can be scripted to fail, and records every
+//! request it receives.
+//!
+//! The mock is not a server. Nothing listens on [`BASE_URL`], so only clients that send their requests through a
+//! `MockVrm` reach it, which are those built with [`MockVrm::builder`] or [`MockVrm::login`]. Clients built any
+//! other way, such as with `Victron::login` or `Victron::from_session`, send their requests over the network, and
+//! so does code that makes HTTP requests without this library.
+//!
+//! ```
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> Result<(), victron_energy_api::Error> {
+//! use victron_energy_api::mock::{MockFailure, MockVrm};
+//!
+//! let vrm = MockVrm::new();
+//! let victron = vrm.builder().login("john@example.com", "password", None, false).await?;
+//!
+//! vrm.fail_next(MockFailure::ServerError);
+//! assert!(victron.get_user_info().await.is_err());
+//!
+//! assert_eq!(vrm.requests().len(), 2);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::VecDeque,
+    future::{ready, Future},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use http::{HeaderMap, StatusCode};
+use serde_json::{json, Value};
+use url::Url;
+
+use crate::{
+    transport::{HttpRequest, HttpResponse, HttpTransport},
+    Error, Method, Victron, VictronBuilder,
+};
+
+/// The base URL the mock serves the API under.
+pub const BASE_URL: &str = "https://vrm.mock/v2";
+
+const USER_FIXTURE: &str = include_str!("mock/user.json");
+const INSTALLATIONS_FIXTURE: &str = include_str!("mock/installations.json");
+const STATS_FIXTURE: &str = include_str!("mock/stats.json");
+const DIAGNOSTICS_FIXTURE: &str = include_str!("mock/diagnostics.json");
+const ALARMS_FIXTURE: &str = include_str!("mock/alarms.json");
+
+#[derive(Debug, Clone, Default)]
+/// An in-process stand-in for the VRM API. See the [module documentation](self).
+///
+/// This is an [`HttpTransport`], not a server, so it only answers clients built with [`MockVrm::builder`].
+///
+/// Clones share the same state, so a clone can be handed to the client while the original is used to script
+/// failures and inspect requests.
+pub struct MockVrm {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    failures: VecDeque<ScriptedFailure>,
+    requests: Vec<RecordedRequest>,
+    tokens: Vec<String>,
+    issued_tokens: usize,
+    verification_code: Option<String>,
+}
+
+#[derive(Debug)]
+struct ScriptedFailure {
+    path: Option<String>,
+    failure: MockFailure,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A failure the mock can be scripted to respond with.
+pub enum MockFailure {
+    /// `401 Unauthorized`, as returned for an expired token.
+    Unauthorized,
+    /// `429 Too Many Requests`.
+    RateLimited,
+    /// `500 Internal Server Error`.
+    ServerError,
+    /// `200 OK` with a body that isn't valid JSON.
+    MalformedJson,
+    /// Any other response.
+    Response { status: u16, body: String },
+}
+
+#[derive(Debug, Clone)]
+/// A request received by the mock.
+pub struct RecordedRequest {
+    pub method: Method,
+    /// The path of the request, relative to [`BASE_URL`].
+    pub path: String,
+    /// The query string parameters, in the order they were sent.
+    pub query: Vec<(String, String)>,
+    pub headers: HeaderMap,
+    /// The JSON body, if the request had one.
+    pub body: Option<Value>,
+}
+
+impl RecordedRequest {
+    #[must_use]
+    /// Returns the value of a query string parameter.
+    pub fn query_param(&self, key: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+impl MockVrm {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    /// Returns a builder that sends requests to this mock. Use [`VictronBuilder::restore`] on it to restore a
+    /// [`Session`](crate::session::Session) of the mock.
+    pub fn builder(&self) -> VictronBuilder<Self> {
+        VictronBuilder::with_transport(self.clone()).base_url(BASE_URL)
+    }
+
+    /// Logs into the mock with any credentials.
+    ///
+    /// # Errors
+    /// Any failure that was scripted for the login request.
+    pub async fn login(&self) -> Result<Victron<Self>, Error> {
+        self.builder()
+            .login("john@example.com", "password", None, false)
+            .await
+    }
+
+    /// Requires logins to be verified with `code`, as if the account had two-factor authentication enabled.
+    pub fn require_verification(&self, code: &str) {
+        self.state().verification_code = Some(code.to_string());
+    }
+
+    /// Makes the next request fail.
+    pub fn fail_next(&self, failure: MockFailure) {
+        self.state().failures.push_back(ScriptedFailure {
+            path: None,
+            failure,
+        });
+    }
+
+    /// Makes the next request whose path contains `path` fail.
+    pub fn fail_next_on(&self, path: &str, failure: MockFailure) {
+        self.state().failures.push_back(ScriptedFailure {
+            path: Some(path.to_string()),
+            failure,
+        });
+    }
+
+    /// Invalidates every token issued so far, so requests fail with `401 Unauthorized` until logging in again.
+    pub fn expire_tokens(&self) {
+        self.state().tokens.clear();
+    }
+
+    #[must_use]
+    /// Returns every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Forgets the requests received so far.
+    pub fn clear_requests(&self) {
+        self.state().requests.clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn handle(&self, request: &HttpRequest) -> Result<HttpResponse, Error> {
+        let url = Url::parse(&request.uri().to_string())?;
+        let path = url
+            .path()
+            .strip_prefix(Url::parse(BASE_URL)?.path())
+            .unwrap_or_else(|| url.path())
+            .to_string();
+
+        let recorded = RecordedRequest {
+            method: request.method().clone(),
+            query: url.query_pairs().into_owned().collect(),
+            headers: request.headers().clone(),
+            body: serde_json::from_slice(request.body()).ok(),
+            path,
+        };
+
+        let mut state = self.state();
+        state.requests.push(recorded.clone());
+
+        if let Some(index) = state.failures.iter().position(|scripted| {
+            scripted
+                .path
+                .as_ref()
+                .is_none_or(|path| recorded.path.contains(path.as_str()))
+        }) {
+            let scripted = state.failures.remove(index);
+            return Ok(scripted.map_or_else(not_found, |scripted| scripted.failure.response()));
+        }
+
+        state.route(&recorded)
+    }
+}
+
+impl State {
+    fn route(&mut self, request: &RecordedRequest) -> Result<HttpResponse, Error> {
+        let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+
+        match (&request.method, segments.as_slice()) {
+            (&Method::POST, ["auth", "login"]) => Ok(self.login(request)),
+            (&Method::POST, ["auth", "loginAsDemo"]) => {
+                let token = self.issue_token();
+                Ok(respond(StatusCode::OK, &json!({ "token": token })))
+            }
+            _ if !self.is_authorized(request) => Ok(respond(
+                StatusCode::UNAUTHORIZED,
+                &json!({ "success": false, "errors": "Invalid token", "error_code": "invalid_token" }),
+            )),
+            (&Method::GET, ["users", "me"]) => Ok(respond(
+                StatusCode::OK,
+                &serde_json::from_str::<Value>(USER_FIXTURE)?,
+            )),
+            (&Method::GET, ["users", _, "installations"]) => {
+                let mut records: Vec<Value> = serde_json::from_str(INSTALLATIONS_FIXTURE)?;
+
+                if let Some(site_id) = request.query_param("idSite") {
+                    let site_id = site_id.parse::<i64>().ok();
+                    records.retain(|record| record["idSite"].as_i64() == site_id);
+                }
+
+                if request.query_param("extended") != Some("1") {
+                    for record in &mut records {
+                        if let Some(record) = record.as_object_mut() {
+                            record.remove("extended");
+                        }
+                    }
+                }
+
+                Ok(respond(
+                    StatusCode::OK,
+                    &json!({ "success": true, "records": records }),
+                ))
+            }
+            (&Method::POST, ["users", _, "addSite"]) => Ok(respond(
+                StatusCode::OK,
+                &json!({ "success": true, "records": { "site_id": 151_736 } }),
+            )),
+            (&Method::GET, ["installations", site_id, endpoint]) => {
+                installation_data(request, site_id, endpoint)
+            }
+            _ => Ok(not_found()),
+        }
+    }
+
+    fn login(&mut self, request: &RecordedRequest) -> HttpResponse {
+        let sms_token = request
+            .body
+            .as_ref()
+            .and_then(|body| body.get("sms_token"))
+            .and_then(Value::as_str);
+
+        let verification_mode = match (&self.verification_code, sms_token) {
+            (None, _) => "password",
+            (Some(code), Some(sms_token)) if code == sms_token => "sms",
+            (Some(_), Some(_)) => {
+                return respond(
+                    StatusCode::UNAUTHORIZED,
+                    &json!({
+                        "success": false,
+                        "errors": "Invalid verification code",
+                        "error_code": "invalid_credentials",
+                    }),
+                );
+            }
+            (Some(_), None) => {
+                return respond(
+                    StatusCode::OK,
+                    &json!({
+                        "token": null,
+                        "idUser": 22,
+                        "verification_mode": "sms",
+                        "verification_sent": true,
+                    }),
+                );
+            }
+        };
+
+        let token = self.issue_token();
+
+        respond(
+            StatusCode::OK,
+            &json!({
+                "token": token,
+                "idUser": 22,
+                "verification_mode": verification_mode,
+                "verification_sent": false,
+            }),
+        )
+    }
+
+    fn issue_token(&mut self) -> String {
+        self.issued_tokens += 1;
+
+        let token = format!("mock-token-{}", self.issued_tokens);
+        self.tokens.push(token.clone());
+        token
+    }
+
+    fn is_authorized(&self, request: &RecordedRequest) -> bool {
+        request
+            .headers
+            .get("x-authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .is_some_and(|(_, token)| self.tokens.iter().any(|issued| issued == token))
+    }
+}
+
+impl MockFailure {
+    fn response(self) -> HttpResponse {
+        match self {
+            Self::Unauthorized => respond(
+                StatusCode::UNAUTHORIZED,
+                &json!({ "success": false, "errors": "Token expired", "error_code": "token_expired" }),
+            ),
+            Self::RateLimited => respond(
+                StatusCode::TOO_MANY_REQUESTS,
+                &json!({ "success": false, "errors": "Too many requests", "error_code": "rate_limited" }),
+            ),
+            Self::ServerError => raw(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "<html><body><h1>500 Internal Server Error</h1></body></html>".to_string(),
+            ),
+            Self::MalformedJson => raw(
+                StatusCode::OK,
+                r#"{"success": true, "records": ["#.to_string(),
+            ),
+            Self::Response { status, body } => raw(
+                StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+                body,
+            ),
+        }
+    }
+}
+
+impl HttpTransport for MockVrm {
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse, Error>> + Send {
+        ready(self.handle(&request))
+    }
+}
+
+/// Serves the stats, diagnostics and alarms of the installations in the fixtures.
+fn installation_data(
+    request: &RecordedRequest,
+    site_id: &str,
+    endpoint: &str,
+) -> Result<HttpResponse, Error> {
+    let installations: Vec<Value> = serde_json::from_str(INSTALLATIONS_FIXTURE)?;
+
+    let Some(site_id) = site_id.parse::<i64>().ok().filter(|site_id| {
+        installations
+            .iter()
+            .any(|installation| installation["idSite"].as_i64() == Some(*site_id))
+    }) else {
+        return Ok(not_found());
+    };
+
+    let body = match endpoint {
+        "stats" => {
+            let mut body: Value = serde_json::from_str(STATS_FIXTURE)?;
+            let codes: Vec<&str> = request
+                .query
+                .iter()
+                .filter(|(key, _)| key == "attributeCodes[]")
+                .map(|(_, code)| code.as_str())
+                .collect();
+
+            if !codes.is_empty() {
+                for key in ["records", "totals"] {
+                    if let Some(values) = body[key].as_object_mut() {
+                        values.retain(|code, _| codes.contains(&code.as_str()));
+                    }
+                }
+            }
+
+            body
+        }
+        "diagnostics" => {
+            let mut body: Value = serde_json::from_str(DIAGNOSTICS_FIXTURE)?;
+
+            for record in body["records"].as_array_mut().into_iter().flatten() {
+                record["idSite"] = json!(site_id);
+            }
+
+            // Serve the page that was asked for.
+            let number = |key| {
+                request
+                    .query_param(key)
+                    .and_then(|value| value.parse().ok())
+            };
+            if let Some(records) = body["records"].as_array_mut() {
+                let offset = number("offset").unwrap_or(0).min(records.len());
+                let count = number("count").unwrap_or(usize::MAX);
+                *records = records.drain(offset..).take(count).collect();
+            }
+
+            body
+        }
+        "alarms" => serde_json::from_str(ALARMS_FIXTURE)?,
+        _ => return Ok(not_found()),
+    };
+
+    Ok(respond(StatusCode::OK, &body))
+}
+
+fn not_found() -> HttpResponse {
+    respond(
+        StatusCode::NOT_FOUND,
+        &json!({ "success": false, "errors": "Not found", "error_code": "not_found" }),
+    )
+}
+
+fn respond(status: StatusCode, body: &Value) -> HttpResponse {
+    raw(status, body.to_string())
+}
+
+fn raw(status: StatusCode, body: String) -> HttpResponse {
+    let mut response = HttpResponse::new(body.into_bytes());
+    *response.status_mut() = status;
+    response
+}
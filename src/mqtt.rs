@@ -0,0 +1,1045 @@
+//! Real-time values of an installation, from the MQTT broker VRM runs for it, or from the GX device itself.
+//!
+//! Every GX device publishes the values of its D-Bus services to VRM as MQTT messages on `N/<portal id>/...`
+//! topics, as long as a client sends keepalives. [`MqttClient`] subscribes to those topics, sends the keepalives
+//! and decodes every message into an [`Update`].
+//!
+//! Users who may control the installation can change values too, with [`MqttClient::write`].
+//!
+//! The GX device runs the same broker on the local network, which keeps working when the internet connection is
+//! down. [`MqttClient::connect_local`] connects to it directly, without VRM.
+//!
+//! The client drives its connection in tokio tasks, so it needs a tokio runtime. Connections to VRM use rustls,
+//! with the options of a [`TlsConfig`]. Local brokers are reached without TLS, see [`MqttOptions::local`].
+//!
+//! ```no_run
+//! use futures_util::StreamExt;
+//! use victron_energy_api::{mqtt::MqttOptions, Victron};
+//!
+//! # async fn run() -> Result<(), victron_energy_api::Error> {
+//! let victron = Victron::login_access_token("me@example.com", "token").await?;
+//! let installation = &victron.get_all_installations_or_sites(false).await?[0];
+//!
+//! let mqtt = victron.connect_mqtt(installation, MqttOptions::new()).await?;
+//! let mut updates = Box::pin(mqtt.updates());
+//!
+//! while let Some(update) = updates.next().await {
+//!     println!("{}: {:?}", update.key, update.value());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
+use futures_util::{stream, Stream};
+use rumqttc::{
+    AsyncClient, ConnectReturnCode, ConnectionError, Event, EventLoop, Outgoing, Packet, QoS,
+    TlsConfiguration, Transport,
+};
+use serde_json::{json, Value};
+use tokio::{sync::broadcast, task::JoinHandle};
+
+use crate::{
+    diagnostics::Diagnostic,
+    installations::{AttributeValue, Data, DataAttribute, Extended, Installation},
+    runtime,
+    tls::TlsConfig,
+    transport::HttpTransport,
+    Error, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
+    /// Connects to the MQTT broker of an installation, authenticating as the logged in user.
+    ///
+    /// The broker is read from [`Installation::mqtt_host`], unless set with [`MqttOptions::host`]. This waits
+    /// until the broker accepted the connection, and must be called within a tokio runtime. The connection uses the
+    /// TLS options this client was built with, unless set with [`MqttOptions::tls`].
+    ///
+    /// Values can only be written if [`Installation::can_write_mqtt`] is true for `installation`.
+    ///
+    /// The password is the token of this client, so reconnecting uses the latest token. If the broker rejects it,
+    /// the client logs in again with its credentials, see [`Victron::with_credentials`]. Without credentials, or if
+    /// the broker rejects the new token as well, the connection is closed.
+    ///
+    /// # Errors
+    /// - [`Error::MqttUnavailable`] if VRM has no broker for the installation.
+    /// - [`Error::Reqwest`] if there was an error fetching the email address of the user.
+    /// - [`Error::MqttConnection`] if the broker could not be reached, or rejected the login.
+    /// - [`Error::Io`] if a certificate or key of the [`TlsConfig`] could not be read.
+    pub async fn connect_mqtt(
+        &self,
+        installation: &Installation,
+        mut options: MqttOptions,
+    ) -> Result<MqttClient, Error> {
+        if options.tls.is_none() {
+            options.tls.clone_from(&self.tls);
+        }
+
+        let host = options
+            .host
+            .clone()
+            .or_else(|| installation.mqtt_host.clone())
+            .ok_or(Error::MqttUnavailable(installation.site_id))?;
+
+        let username = self.get_user_info().await?.email;
+        let password = self.token.read().await.to_string();
+
+        MqttClient::connect(
+            &host,
+            &options,
+            Some((username, password)),
+            Some(self.mqtt_password()),
+            Some(&installation.identifier),
+            installation.can_write_mqtt(),
+        )
+        .await
+    }
+
+    /// Returns the current token as the password of the broker, logging in again first if the broker rejected it.
+    fn mqtt_password(&self) -> Password {
+        let victron = self.clone();
+
+        Box::new(move |rejected| {
+            let victron = victron.clone();
+
+            Box::pin(async move {
+                let token = victron.token.read().await.clone();
+                if rejected && !victron.reauthenticate(&token).await? {
+                    return Ok(None);
+                }
+
+                Ok(Some(victron.token.read().await.to_string()))
+            })
+        })
+    }
+}
+
+/// Returns the password to log in to the broker with when reconnecting, after logging in again if the broker
+/// rejected the last one. `None` if there is no other password to try.
+type Password = Box<
+    dyn Fn(bool) -> Pin<Box<dyn Future<Output = Result<Option<String>, Error>> + Send>>
+        + Send
+        + Sync,
+>;
+
+#[derive(Clone)]
+/// How [`Victron::connect_mqtt`] or [`MqttClient::connect_local`] connects to a broker.
+pub struct MqttOptions {
+    host: Option<String>,
+    port: u16,
+    /// The TLS options set with [`MqttOptions::tls`].
+    tls: Option<TlsConfig>,
+    plaintext: bool,
+    credentials: Option<(String, String)>,
+    keepalive_interval: Duration,
+    write_timeout: Duration,
+    discovery_timeout: Duration,
+    local_writes: bool,
+}
+
+impl MqttOptions {
+    /// The port of the VRM brokers, which only accept TLS connections.
+    pub const VRM_PORT: u16 = 8883;
+    /// The port of the plaintext broker of a GX device.
+    pub const LOCAL_PORT: u16 = 1883;
+
+    #[must_use]
+    /// Connects to the broker of the installation over TLS, trusting the built-in root certificates.
+    pub const fn new() -> Self {
+        Self {
+            host: None,
+            port: Self::VRM_PORT,
+            tls: None,
+            plaintext: false,
+            credentials: None,
+            keepalive_interval: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(10),
+            discovery_timeout: Duration::from_secs(10),
+            local_writes: false,
+        }
+    }
+
+    #[must_use]
+    /// Connects to the broker of a GX device on the local network, without TLS.
+    pub const fn local() -> Self {
+        Self {
+            host: None,
+            port: Self::LOCAL_PORT,
+            tls: None,
+            plaintext: true,
+            credentials: None,
+            keepalive_interval: Duration::from_secs(30),
+            write_timeout: Duration::from_secs(10),
+            discovery_timeout: Duration::from_secs(10),
+            local_writes: false,
+        }
+    }
+
+    #[must_use]
+    /// Connects to another broker than the one VRM assigned to the installation, such as a local broker in tests.
+    pub fn host(mut self, host: &str) -> Self {
+        self.host = Some(host.to_string());
+        self
+    }
+
+    #[must_use]
+    /// Sets the port of the broker. Defaults to [`MqttOptions::VRM_PORT`] for [`MqttOptions::new`], and to
+    /// [`MqttOptions::LOCAL_PORT`] for [`MqttOptions::local`].
+    pub const fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    #[must_use]
+    /// Connects over TLS with these options, such as an additional root certificate to trust.
+    ///
+    /// Without this, [`Victron::connect_mqtt`] uses the options the client was built with, if any were set with
+    /// `VictronBuilder::tls`, and otherwise trusts the built-in root certificates.
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self.plaintext = false;
+        self
+    }
+
+    #[must_use]
+    /// Connects without TLS, which only local brokers accept.
+    pub fn plaintext(mut self) -> Self {
+        self.tls = None;
+        self.plaintext = true;
+        self
+    }
+
+    #[must_use]
+    /// Logs in to a local broker that requires it, such as a GX device with a password set.
+    ///
+    /// [`Victron::connect_mqtt`] ignores these, and always logs in as the VRM user.
+    pub fn credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    #[must_use]
+    /// Sets how often a keepalive is sent. The GX device stops publishing after 60 seconds without one, so this
+    /// should stay well below that. Defaults to 30 seconds.
+    pub const fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    #[must_use]
+    /// Sets how long [`MqttClient::write`] waits for the GX device to report the written value. Defaults to 10
+    /// seconds.
+    pub const fn write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    /// Sets how long [`MqttClient::connect_local`] waits for the GX device to announce its portal id after
+    /// connecting. Defaults to 10 seconds.
+    pub const fn discovery_timeout(mut self, timeout: Duration) -> Self {
+        self.discovery_timeout = timeout;
+        self
+    }
+
+    #[must_use]
+    /// Allows [`MqttClient::write`] on a client created with [`MqttClient::connect_local`]. Disabled by default.
+    ///
+    /// The local broker doesn't check who may control the installation, so this is left to the caller.
+    /// [`Victron::connect_mqtt`] ignores this, and uses the permissions of the VRM user instead.
+    pub const fn local_writes(mut self, enabled: bool) -> Self {
+        self.local_writes = enabled;
+        self
+    }
+}
+
+impl fmt::Debug for MqttOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttOptions")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("tls", &self.tls)
+            .field("plaintext", &self.plaintext)
+            .field("credentials", &self.credentials.as_ref().map(|_| ".."))
+            .field("keepalive_interval", &self.keepalive_interval)
+            .field("write_timeout", &self.write_timeout)
+            .field("discovery_timeout", &self.discovery_timeout)
+            .field("local_writes", &self.local_writes)
+            .finish()
+    }
+}
+
+impl Default for MqttOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A connection to the MQTT broker of an installation. Created with [`Victron::connect_mqtt`].
+///
+/// Clones share the connection, which is closed when the last clone is dropped. The connection is re-established
+/// automatically when it drops.
+pub struct MqttClient {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: AsyncClient,
+    portal_id: String,
+    /// Whether the user may change values of the GX device.
+    writable: bool,
+    write_timeout: Duration,
+    /// Only used to subscribe to the updates, which are sent by the task that drives the connection. That task
+    /// holds the only sender, so the updates end when it does.
+    updates: broadcast::Receiver<Update>,
+    values: Arc<Mutex<HashMap<ValueKey, Update>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// How many updates a slow subscriber of [`MqttClient::updates`] can fall behind before it misses some. A GX device
+/// publishes all of its values at once after connecting, which can be a few thousand.
+const UPDATE_CAPACITY: usize = 8192;
+
+/// How long to wait before reconnecting after the connection failed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// The topic on which a GX device announces its serial number, which is its portal id.
+const SERIAL_TOPIC: &str = "N/+/system/0/Serial";
+
+impl MqttClient {
+    /// Connects to the broker of a GX device on the local network, by IP address or host name.
+    ///
+    /// Use [`MqttOptions::local`] for the plaintext broker, which has to be enabled in the settings of the GX
+    /// device. The portal id is discovered from the device. This waits until the device announced it, and must
+    /// be called within a tokio runtime.
+    ///
+    /// Values can only be written if enabled with [`MqttOptions::local_writes`].
+    ///
+    /// # Errors
+    /// - [`Error::MqttConnection`] if the broker could not be reached, or rejected the login.
+    /// - [`Error::MqttDiscoveryTimeout`] if the device didn't announce its portal id within
+    ///   [`MqttOptions::discovery_timeout`].
+    /// - [`Error::Io`] if a certificate or key of the [`TlsConfig`] could not be read.
+    pub async fn connect_local(host: &str, options: MqttOptions) -> Result<Self, Error> {
+        Self::connect(
+            host,
+            &options,
+            options.credentials.clone(),
+            None,
+            None,
+            options.local_writes,
+        )
+        .await
+    }
+
+    /// Connects to a broker and waits until it accepted the connection, then keeps the connection alive in the
+    /// background. Without a portal id, it is discovered first.
+    ///
+    /// Reconnecting uses the same credentials, with the password from `password` if there is one.
+    async fn connect(
+        host: &str,
+        options: &MqttOptions,
+        credentials: Option<(String, String)>,
+        password: Option<Password>,
+        portal_id: Option<&str>,
+        writable: bool,
+    ) -> Result<Self, Error> {
+        let client_id = format!("victron-energy-api-{}", runtime::unix_time().as_nanos());
+
+        let mut mqtt_options = rumqttc::MqttOptions::new(client_id, host, options.port);
+        mqtt_options.set_keep_alive(Duration::from_secs(30));
+
+        if let Some((username, password)) = credentials {
+            mqtt_options.set_credentials(username, password);
+        }
+
+        if !options.plaintext {
+            let tls = options.tls.clone().unwrap_or_default();
+            mqtt_options.set_transport(Transport::Tls(TlsConfiguration::Rustls(tls.to_rustls()?)));
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(mqtt_options, 64);
+
+        loop {
+            if let Event::Incoming(Packet::ConnAck(_)) = event_loop.poll().await? {
+                break;
+            }
+        }
+
+        let portal_id = match portal_id {
+            Some(portal_id) => portal_id.to_string(),
+            None => discover_portal_id(&client, &mut event_loop, options.discovery_timeout).await?,
+        };
+
+        let topics = Topics::new(&portal_id);
+        topics.subscribe(&client)?;
+
+        let (sender, updates) = broadcast::channel(UPDATE_CAPACITY);
+        let values = Arc::new(Mutex::new(HashMap::new()));
+
+        let tasks = vec![
+            tokio::spawn(drive(
+                event_loop,
+                client.clone(),
+                topics.clone(),
+                password,
+                sender,
+                Arc::clone(&values),
+            )),
+            tokio::spawn(keepalive(
+                client.clone(),
+                topics,
+                options.keepalive_interval,
+            )),
+        ];
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                client,
+                portal_id,
+                writable,
+                write_timeout: options.write_timeout,
+                updates,
+                values,
+                tasks,
+            }),
+        })
+    }
+
+    #[must_use]
+    /// Returns the VRM portal id of the GX device, which is the [`Installation::identifier`].
+    pub fn portal_id(&self) -> &str {
+        &self.inner.portal_id
+    }
+
+    /// Returns the updates received from now on.
+    ///
+    /// Every call returns a separate stream, which ends when the connection is closed, or the broker rejected the
+    /// login when reconnecting. A stream that falls far behind skips the updates it missed; [`MqttClient::value`]
+    /// always has the latest ones.
+    pub fn updates(&self) -> impl Stream<Item = Update> {
+        stream::unfold(self.inner.updates.resubscribe(), |mut updates| async move {
+            loop {
+                match updates.recv().await {
+                    Ok(update) => return Some((update, updates)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "MQTT update stream fell behind");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    #[must_use]
+    /// Returns the latest update of a value, if one was received.
+    pub fn value(&self, key: &ValueKey) -> Option<Update> {
+        lock(&self.inner.values).get(key).cloned()
+    }
+
+    #[must_use]
+    /// Returns the latest update of every value received so far.
+    pub fn values(&self) -> Vec<Update> {
+        lock(&self.inner.values).values().cloned().collect()
+    }
+
+    /// Changes a value on the GX device, such as a setting or the state of a relay, and waits until the device
+    /// reports it.
+    ///
+    /// The device may report the old value first, before the change took effect, so this waits for the written
+    /// value until [`MqttOptions::write_timeout`] has passed. If the device reported another value by then, the
+    /// write was [rejected](WriteOutcome::Rejected) or [adjusted](WriteOutcome::Adjusted), for example because the
+    /// value was out of range.
+    ///
+    /// # Errors
+    /// - [`Error::MqttWriteNotPermitted`] if the user may not control the installation, or writes to a local broker
+    ///   weren't enabled with [`MqttOptions::local_writes`].
+    /// - [`Error::Mqtt`] if the connection was closed before the change was sent.
+    /// - [`Error::MqttClosed`] if the connection was closed before the device reported the value.
+    /// - [`Error::MqttWriteTimeout`] if the device didn't report the value at all within
+    ///   [`MqttOptions::write_timeout`].
+    pub async fn write(
+        &self,
+        key: &ValueKey,
+        value: impl Into<Value>,
+    ) -> Result<WriteOutcome, Error> {
+        if !self.inner.writable {
+            return Err(Error::MqttWriteNotPermitted);
+        }
+
+        // Subscribe before writing, so the confirmation can't be missed.
+        let mut updates = self.inner.updates.resubscribe();
+        let previous = self.value(key).map(|update| update.raw_value);
+
+        let written = value.into();
+        let payload = serde_json::to_vec(&json!({ "value": written }))?;
+        let client = &self.inner.client;
+
+        client
+            .publish(
+                key.topic('W', self.portal_id()),
+                QoS::AtLeastOnce,
+                false,
+                payload,
+            )
+            .await?;
+        // The device only publishes values that changed, so ask for the value in case it was already set.
+        client
+            .publish(key.topic('R', self.portal_id()), QoS::AtMostOnce, false, "")
+            .await?;
+
+        // The latest value the device reported, if it isn't the written one.
+        let mut latest = None;
+
+        let confirmation = async {
+            loop {
+                match updates.recv().await {
+                    Ok(update) if update.key == *key => {
+                        match WriteOutcome::new(&written, previous.as_ref(), update) {
+                            WriteOutcome::Confirmed(update) => {
+                                return Ok(WriteOutcome::Confirmed(update))
+                            }
+                            outcome => latest = Some(outcome),
+                        }
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => return Err(Error::MqttClosed),
+                }
+            }
+        };
+
+        tokio::time::timeout(self.inner.write_timeout, confirmation)
+            .await
+            .unwrap_or_else(|_| latest.ok_or_else(|| Error::MqttWriteTimeout(key.clone())))
+    }
+
+    /// Sets the minimum state of charge of the battery in ESS mode, in percent. See [`MqttClient::write`].
+    ///
+    /// # Errors
+    /// See [`MqttClient::write`].
+    pub async fn set_ess_minimum_soc(&self, percent: f64) -> Result<WriteOutcome, Error> {
+        self.write(&ValueKey::ess_minimum_soc(), percent).await
+    }
+
+    /// Switches a relay of the GX device on or off. The first relay is number 0. See [`MqttClient::write`].
+    ///
+    /// # Errors
+    /// See [`MqttClient::write`].
+    pub async fn set_relay(&self, relay: u32, on: bool) -> Result<WriteOutcome, Error> {
+        self.write(&ValueKey::relay_state(relay), i32::from(on))
+            .await
+    }
+
+    /// Closes the connection. The streams of [`MqttClient::updates`] end, including those of other clones.
+    ///
+    /// # Errors
+    /// - [`Error::Mqtt`] if the connection was already closed.
+    pub async fn disconnect(&self) -> Result<(), Error> {
+        Ok(self.inner.client.disconnect().await?)
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttClient")
+            .field("portal_id", &self.portal_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The topics of a GX device.
+struct Topics {
+    notifications: String,
+    keepalive: String,
+}
+
+impl Topics {
+    fn new(portal_id: &str) -> Self {
+        Self {
+            notifications: format!("N/{portal_id}/#"),
+            keepalive: format!("R/{portal_id}/keepalive"),
+        }
+    }
+
+    /// Subscribes to the values of the GX device, and asks it to publish all of them.
+    ///
+    /// This doesn't wait for the event loop, so it can be called from within it.
+    fn subscribe(&self, client: &AsyncClient) -> Result<(), Error> {
+        client.try_subscribe(&self.notifications, QoS::AtMostOnce)?;
+        client.try_publish(&self.keepalive, QoS::AtMostOnce, false, "")?;
+
+        Ok(())
+    }
+}
+
+/// Waits up to `timeout` for the GX device to announce its serial number, and returns it as the portal id.
+async fn discover_portal_id(
+    client: &AsyncClient,
+    event_loop: &mut EventLoop,
+    timeout: Duration,
+) -> Result<String, Error> {
+    client.try_subscribe(SERIAL_TOPIC, QoS::AtMostOnce)?;
+
+    let discovery = async {
+        loop {
+            if let Event::Incoming(Packet::Publish(publish)) = event_loop.poll().await? {
+                if let Some(portal_id) = publish.topic.split('/').nth(1) {
+                    return Ok::<_, Error>(portal_id.to_string());
+                }
+            }
+        }
+    };
+
+    let portal_id = tokio::time::timeout(timeout, discovery)
+        .await
+        .map_err(|_| Error::MqttDiscoveryTimeout)??;
+
+    client.try_unsubscribe(SERIAL_TOPIC)?;
+
+    Ok(portal_id)
+}
+
+/// Receives messages and reconnects when needed, until the client is dropped or the broker rejects the login.
+async fn drive(
+    mut event_loop: EventLoop,
+    client: AsyncClient,
+    topics: Topics,
+    password: Option<Password>,
+    updates: broadcast::Sender<Update>,
+    values: Arc<Mutex<HashMap<ValueKey, Update>>>,
+) {
+    // Whether the password was replaced after the broker rejected the one before, so it isn't replaced forever.
+    let mut replaced_rejected = false;
+
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                let Some(update) = Update::from_message(&publish.topic, &publish.payload) else {
+                    continue;
+                };
+
+                lock(&values).insert(update.key.clone(), update.clone());
+                // Nobody may be listening, which is fine.
+                let _ = updates.send(update);
+            }
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                replaced_rejected = false;
+
+                if let Err(error) = topics.subscribe(&client) {
+                    tracing::warn!(%error, "could not subscribe after reconnecting to the MQTT broker");
+                }
+            }
+            Ok(Event::Outgoing(Outgoing::Disconnect)) | Err(ConnectionError::RequestsDone) => {
+                return
+            }
+            Ok(_) => {}
+            Err(ConnectionError::ConnectionRefused(
+                ConnectReturnCode::BadUserNamePassword | ConnectReturnCode::NotAuthorized,
+            )) => {
+                let Some(password) = password.as_ref().filter(|_| !replaced_rejected) else {
+                    tracing::warn!("MQTT broker rejected the login, closing the connection");
+                    return;
+                };
+
+                match password(true).await {
+                    Ok(Some(password)) => {
+                        set_password(&mut event_loop, password);
+                        replaced_rejected = true;
+                    }
+                    Ok(None) => {
+                        tracing::warn!("MQTT broker rejected the login, closing the connection");
+                        return;
+                    }
+                    Err(error) => {
+                        tracing::warn!(%error, "could not log in again for the MQTT broker, reconnecting");
+                        runtime::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+            Err(error) => {
+                tracing::warn!(%error, "MQTT connection failed, reconnecting");
+                runtime::sleep(RECONNECT_DELAY).await;
+
+                if let Some(password) = &password {
+                    match password(false).await {
+                        Ok(Some(password)) => set_password(&mut event_loop, password),
+                        Ok(None) => {}
+                        Err(error) => tracing::warn!(%error, "could not get the MQTT password"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Replaces the password the event loop logs in with when it reconnects.
+fn set_password(event_loop: &mut EventLoop, password: String) {
+    if let Some((username, _)) = event_loop.mqtt_options.credentials() {
+        event_loop.mqtt_options.set_credentials(username, password);
+    }
+}
+
+/// Sends keepalives, so the GX device keeps publishing. Only the first one asks it to publish every value again.
+async fn keepalive(client: AsyncClient, topics: Topics, interval: Duration) {
+    const SUPPRESS_REPUBLISH: &str = r#"{"keepalive-options": ["suppress-republish"]}"#;
+
+    loop {
+        runtime::sleep(interval).await;
+
+        if let Err(error) = client
+            .publish(
+                &topics.keepalive,
+                QoS::AtMostOnce,
+                false,
+                SUPPRESS_REPUBLISH,
+            )
+            .await
+        {
+            tracing::warn!(%error, "could not send MQTT keepalive");
+            return;
+        }
+    }
+}
+
+fn lock<V>(mutex: &Mutex<V>) -> MutexGuard<'_, V> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Where a value lives on the GX device: the type of its D-Bus service, the device instance and the D-Bus path.
+///
+/// These match [`crate::installations::Data::dbus_service_type`] and [`crate::installations::Data::dbus_path`].
+pub struct ValueKey {
+    /// The type of D-Bus service, such as `battery` or `solarcharger`.
+    pub service_type: String,
+    /// The device instance, to tell apart devices of the same type.
+    pub instance: u32,
+    /// The D-Bus path, such as `/Dc/0/Voltage`.
+    pub path: String,
+}
+
+impl ValueKey {
+    #[must_use]
+    /// Creates a key, adding the leading `/` to `path` if it is missing.
+    pub fn new(service_type: &str, instance: u32, path: &str) -> Self {
+        Self {
+            service_type: service_type.to_string(),
+            instance,
+            path: format!("/{}", path.trim_start_matches('/')),
+        }
+    }
+
+    #[must_use]
+    /// The minimum state of charge of the battery in ESS mode, in percent.
+    pub fn ess_minimum_soc() -> Self {
+        Self::new(
+            "settings",
+            0,
+            "/Settings/CGwacs/BatteryLife/MinimumSocLimit",
+        )
+    }
+
+    #[must_use]
+    /// The state of a relay of the GX device, 1 if it is on and 0 if it is off. The first relay is number 0.
+    pub fn relay_state(relay: u32) -> Self {
+        Self::new("system", 0, &format!("/Relay/{relay}/State"))
+    }
+
+    #[must_use]
+    /// Returns the key of the value VRM logged as `data`, if VRM knows where it lives on the GX device.
+    pub fn of_data(data: &Data) -> Option<Self> {
+        Self::from_dbus(
+            &data.dbus_service_type,
+            data.instance.parse().ok()?,
+            &data.dbus_path,
+        )
+    }
+
+    #[must_use]
+    /// Returns the key of one of the values a [`crate::installations::Summary`] is made of.
+    pub fn of_data_attribute(attribute: &DataAttribute) -> Option<Self> {
+        Self::from_dbus(
+            &attribute.dbus_service_type,
+            attribute.instance.try_into().ok()?,
+            &attribute.dbus_path,
+        )
+    }
+
+    #[must_use]
+    /// Returns the keys of the values behind an extended attribute of an installation, such as the result of
+    /// [`Installation::attribute`].
+    pub fn of_extended(extended: &Extended) -> Vec<Self> {
+        match extended {
+            Extended::Data(data) => Self::of_data(data).into_iter().collect(),
+            Extended::Summary(summary) => summary
+                .data_attributes
+                .iter()
+                .filter_map(Self::of_data_attribute)
+                .collect(),
+        }
+    }
+
+    #[must_use]
+    /// Returns the key of the value of a diagnostic, if VRM knows where it lives on the GX device.
+    pub fn of_diagnostic(diagnostic: &Diagnostic) -> Option<Self> {
+        Self::from_dbus(
+            diagnostic.dbus_service_type.as_deref()?,
+            diagnostic.instance.try_into().ok()?,
+            diagnostic.dbus_path.as_deref()?,
+        )
+    }
+
+    /// Creates a key from the D-Bus service type and path VRM sent, which are empty for values that don't come from
+    /// the GX device.
+    fn from_dbus(service_type: &str, instance: u32, path: &str) -> Option<Self> {
+        (!service_type.is_empty() && !path.is_empty())
+            .then(|| Self::new(service_type, instance, path))
+    }
+
+    /// Returns the topic of this value for `prefix`, such as `W` to write it.
+    fn topic(&self, prefix: char, portal_id: &str) -> String {
+        format!("{prefix}/{portal_id}/{self}")
+    }
+
+    /// Reads a key from a topic such as `N/<portal id>/battery/512/Dc/0/Voltage`.
+    fn from_topic(topic: &str) -> Option<Self> {
+        let mut parts = topic.splitn(5, '/');
+
+        let _prefix = parts.next()?;
+        let _portal_id = parts.next()?;
+        let service_type = parts.next()?;
+        let instance = parts.next()?.parse().ok()?;
+        let path = parts.next()?;
+
+        Some(Self::new(service_type, instance, path))
+    }
+}
+
+impl fmt::Display for ValueKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}{}", self.service_type, self.instance, self.path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A value published by the GX device.
+pub struct Update {
+    pub key: ValueKey,
+    /// The value as sent, which is [`Value::Null`] if the value is invalid, or the device is gone.
+    pub raw_value: Value,
+}
+
+impl Update {
+    /// Decodes a message, which has a payload like `{"value": 12.5}`, or no payload if the value was removed.
+    fn from_message(topic: &str, payload: &[u8]) -> Option<Self> {
+        let key = ValueKey::from_topic(topic)?;
+
+        let raw_value = if payload.is_empty() {
+            Value::Null
+        } else {
+            let Ok(Value::Object(mut message)) = serde_json::from_slice::<Value>(payload) else {
+                tracing::warn!(topic, "ignoring MQTT message that is not a value");
+                return None;
+            };
+
+            message.remove("value").unwrap_or_default()
+        };
+
+        Some(Self { key, raw_value })
+    }
+
+    #[must_use]
+    /// Returns the value, or `None` if it is invalid, removed, or not a number or text.
+    pub fn value(&self) -> Option<AttributeValue> {
+        match &self.raw_value {
+            Value::Number(number) => number.as_f64().map(AttributeValue::Numeric),
+            Value::String(text) => Some(AttributeValue::Text(text.clone())),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What the GX device reported after [`MqttClient::write`].
+pub enum WriteOutcome {
+    /// The device reports the written value.
+    Confirmed(Update),
+    /// The device still reports the value it had before the write, for example because the value was out of range
+    /// or the setting can't be changed.
+    Rejected(Update),
+    /// The device reports another value than written, for example because it rounded or limited it. This is also
+    /// the outcome if the value before the write wasn't known.
+    Adjusted(Update),
+}
+
+impl WriteOutcome {
+    /// Classifies an update of a value after `written` was written to it, when it was `previous` before.
+    fn new(written: &Value, previous: Option<&Value>, update: Update) -> Self {
+        if same_value(written, &update.raw_value) {
+            Self::Confirmed(update)
+        } else if previous.is_some_and(|previous| same_value(previous, &update.raw_value)) {
+            Self::Rejected(update)
+        } else {
+            Self::Adjusted(update)
+        }
+    }
+
+    #[must_use]
+    /// Returns the update the device sent, whatever the outcome.
+    pub const fn update(&self) -> &Update {
+        match self {
+            Self::Confirmed(update) | Self::Rejected(update) | Self::Adjusted(update) => update,
+        }
+    }
+
+    #[must_use]
+    /// True if the device reports the written value.
+    pub const fn is_confirmed(&self) -> bool {
+        matches!(self, Self::Confirmed(_))
+    }
+}
+
+/// Compares values, treating numbers as equal if they are, whether they were sent as integers or floats.
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a.as_f64(), b.as_f64()) {
+        (Some(a), Some(b)) => (a - b).abs() <= f64::EPSILON * a.abs().max(b.abs()).max(1.0),
+        _ => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTAL_ID: &str = "c0619ab1e2f3";
+
+    fn update(key: &ValueKey, raw_value: Value) -> Update {
+        Update {
+            key: key.clone(),
+            raw_value,
+        }
+    }
+
+    #[test]
+    fn reads_keys_from_topics() {
+        let key =
+            ValueKey::from_topic("N/c0619ab1e2f3/battery/512/Dc/0/Voltage").expect("valid topic");
+
+        assert_eq!(key, ValueKey::new("battery", 512, "/Dc/0/Voltage"));
+        assert_eq!(key.to_string(), "battery/512/Dc/0/Voltage");
+    }
+
+    #[test]
+    fn ignores_topics_without_a_value() {
+        assert_eq!(
+            ValueKey::from_topic("N/c0619ab1e2f3/full_publish_completed"),
+            None
+        );
+        assert_eq!(
+            ValueKey::from_topic("N/c0619ab1e2f3/battery/first/Soc"),
+            None
+        );
+        assert_eq!(
+            Update::from_message(
+                "N/c0619ab1e2f3/full_publish_completed",
+                br#"{"full-publish-completed-echo": "abc"}"#
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn write_and_read_topics_round_trip() {
+        let key = ValueKey::ess_minimum_soc();
+
+        for prefix in ['W', 'R'] {
+            let topic = key.topic(prefix, PORTAL_ID);
+
+            assert_eq!(
+                topic,
+                format!(
+                    "{prefix}/c0619ab1e2f3/settings/0/Settings/CGwacs/BatteryLife/MinimumSocLimit"
+                )
+            );
+            assert_eq!(ValueKey::from_topic(&topic), Some(key.clone()));
+        }
+    }
+
+    #[test]
+    fn decodes_messages() {
+        let topic = "N/c0619ab1e2f3/battery/512/Dc/0/Voltage";
+        let key = ValueKey::new("battery", 512, "/Dc/0/Voltage");
+
+        let voltage = Update::from_message(topic, br#"{"value": 52.6}"#).expect("valid message");
+        assert_eq!(voltage, update(&key, json!(52.6)));
+        assert_eq!(voltage.value(), Some(AttributeValue::Numeric(52.6)));
+
+        let removed = Update::from_message(topic, b"").expect("valid message");
+        assert_eq!(removed, update(&key, Value::Null));
+        assert_eq!(removed.value(), None);
+
+        let without_value = Update::from_message(topic, b"{}").expect("valid message");
+        assert_eq!(without_value, update(&key, Value::Null));
+    }
+
+    #[test]
+    fn ignores_payloads_that_are_not_objects() {
+        let topic = "N/c0619ab1e2f3/battery/512/Dc/0/Voltage";
+
+        for payload in [
+            &b"52.6"[..],
+            b"[52.6]",
+            b"\"52.6\"",
+            b"null",
+            b"{\"value\":",
+        ] {
+            assert_eq!(Update::from_message(topic, payload), None);
+        }
+    }
+
+    #[test]
+    fn classifies_writes() {
+        let key = ValueKey::ess_minimum_soc();
+        let written = json!(20.0);
+        let previous = json!(10);
+
+        assert!(matches!(
+            WriteOutcome::new(&written, Some(&previous), update(&key, json!(20))),
+            WriteOutcome::Confirmed(_)
+        ));
+        assert!(matches!(
+            WriteOutcome::new(&written, Some(&previous), update(&key, json!(10.0))),
+            WriteOutcome::Rejected(_)
+        ));
+        assert!(matches!(
+            WriteOutcome::new(&written, Some(&previous), update(&key, json!(25))),
+            WriteOutcome::Adjusted(_)
+        ));
+        assert!(matches!(
+            WriteOutcome::new(&written, None, update(&key, json!(10))),
+            WriteOutcome::Adjusted(_)
+        ));
+        assert!(matches!(
+            WriteOutcome::new(&json!("on"), None, update(&key, json!("on"))),
+            WriteOutcome::Confirmed(_)
+        ));
+    }
+}
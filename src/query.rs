@@ -0,0 +1,57 @@
+use std::fmt::Display;
+
+use crate::datetime::TimeRange;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Query string parameters for a request to VRM.
+///
+/// VRM ignores the body of `GET` requests, so every parameter of a `GET` endpoint is passed through this.
+pub struct Query {
+    pairs: Vec<(String, String)>,
+}
+
+impl Query {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { pairs: Vec::new() }
+    }
+
+    #[must_use]
+    /// Adds a parameter.
+    pub fn param(mut self, key: &str, value: impl Display) -> Self {
+        self.pairs.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    #[must_use]
+    /// Adds a parameter, if there is a value.
+    pub fn optional(self, key: &str, value: Option<impl Display>) -> Self {
+        match value {
+            Some(value) => self.param(key, value),
+            None => self,
+        }
+    }
+
+    #[must_use]
+    /// Adds a boolean parameter, which VRM expects as `1` or `0`.
+    pub fn flag(self, key: &str, value: bool) -> Self {
+        self.param(key, u8::from(value))
+    }
+
+    #[must_use]
+    /// Adds the `start` and `end` parameters of a time range.
+    pub fn range(self, range: TimeRange) -> Self {
+        self.param("start", range.start).param("end", range.end)
+    }
+
+    #[must_use]
+    /// Returns the parameters as key-value pairs, in the order they were added.
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use crate::runtime;
+
+/// Configuration for the client-side token-bucket rate limiter.
+///
+/// The bucket starts full, holds at most `burst` requests, and refills at `per_second` requests per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// The maximum number of requests that can be sent in a burst.
+    pub burst: u32,
+    /// How many requests are added back to the bucket every second.
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    /// The request budget documented by VRM: bursts of up to 200 requests, refilling at 3 requests per second.
+    pub const VRM: Self = Self::new(200, 3.0);
+
+    #[must_use]
+    /// Creates a new rate limit with the given burst size and refill rate.
+    ///
+    /// A `burst` of 0 is raised to 1, as no request could ever be sent otherwise.
+    ///
+    /// # Panics
+    /// If `per_second` is not a positive number, as the bucket would never refill.
+    pub const fn new(burst: u32, per_second: f64) -> Self {
+        Self {
+            burst: if burst == 0 { 1 } else { burst },
+            per_second: check_per_second(per_second),
+        }
+    }
+}
+
+/// Panics if `per_second` is zero, negative or NaN, so the mistake surfaces where the limit is set.
+const fn check_per_second(per_second: f64) -> f64 {
+    assert!(
+        per_second > 0.0,
+        "the refill rate of a rate limit must be a positive number"
+    );
+
+    per_second
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::VRM
+    }
+}
+
+/// A token bucket shared between all clones of a [`crate::Victron`].
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a full bucket. `limit` is checked like [`RateLimit::new`] does, as its fields can be set directly.
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        let limit = RateLimit::new(limit.burst, limit.per_second);
+
+        Self {
+            limit,
+            bucket: Mutex::new(Bucket {
+                tokens: f64::from(limit.burst),
+                last_refill: runtime::now(),
+            }),
+        }
+    }
+
+    /// Waits until a request may be sent, then takes a token from the bucket.
+    pub(crate) async fn acquire(&self) {
+        while let Some(wait) = self.try_acquire() {
+            runtime::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long to wait until one will be.
+    fn try_acquire(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let now = runtime::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = elapsed
+            .mul_add(self.limit.per_second, bucket.tokens)
+            .min(f64::from(self.limit.burst));
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return None;
+        }
+
+        // A tiny refill rate can need longer than a `Duration` holds, so poll once a second then.
+        Some(
+            Duration::try_from_secs_f64((1.0 - bucket.tokens) / self.limit.per_second)
+                .unwrap_or(Duration::from_secs(1)),
+        )
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+
+    /// Sends `count` requests, and returns how long that took.
+    async fn send(limiter: &RateLimiter, count: u32) -> Duration {
+        let start = tokio::time::Instant::now();
+
+        for _ in 0..count {
+            limiter.acquire().await;
+        }
+
+        start.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bursts_without_waiting() {
+        let limiter = RateLimiter::new(RateLimit::new(5, 1.0));
+
+        assert_eq!(send(&limiter, 5).await, Duration::ZERO);
+        assert_eq!(send(&limiter, 1).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills_at_the_rate() {
+        let limiter = RateLimiter::new(RateLimit::new(2, 4.0));
+        send(&limiter, 2).await;
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(send(&limiter, 2).await, Duration::ZERO);
+
+        // The bucket doesn't hold more than the burst, however long it waited.
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(send(&limiter, 3).await, Duration::from_millis(250));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn zero_burst_allows_one_request() {
+        let limiter = RateLimiter::new(RateLimit {
+            burst: 0,
+            per_second: 10.0,
+        });
+
+        assert_eq!(send(&limiter, 1).await, Duration::ZERO);
+        assert_eq!(send(&limiter, 1).await, Duration::from_millis(100));
+    }
+
+    #[test]
+    #[should_panic = "positive"]
+    fn rejects_a_rate_of_zero() {
+        let _ = RateLimit::new(10, 0.0);
+    }
+
+    #[test]
+    #[should_panic = "positive"]
+    fn rejects_a_negative_rate() {
+        let _ = RateLimiter::new(RateLimit {
+            burst: 10,
+            per_second: -1.0,
+        });
+    }
+
+    #[test]
+    #[should_panic = "positive"]
+    fn rejects_a_rate_that_is_not_a_number() {
+        let _ = RateLimit::new(10, f64::NAN);
+    }
+}
@@ -0,0 +1,41 @@
+//! The few things the client needs from an async runtime and the platform, so it isn't tied to either.
+
+use std::time::Duration;
+
+/// Waits for `duration` to pass.
+///
+/// Inside a tokio runtime this uses tokio's timer, so it works with tokio's paused time in tests. Everywhere else,
+/// including on other runtimes when the `tokio` feature was enabled by another crate, it uses a
+/// runtime-independent timer. In the browser, that timer is backed by `setTimeout`.
+pub async fn sleep(duration: Duration) {
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        tokio::time::sleep(duration).await;
+        return;
+    }
+
+    futures_timer::Delay::new(duration).await;
+}
+
+/// Returns the current time, for measuring how long something took.
+///
+/// Inside a tokio runtime this reads tokio's clock, which stands still while time is paused in tests, like
+/// [`sleep`] does.
+pub fn now() -> web_time::Instant {
+    #[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return tokio::time::Instant::now().into_std();
+    }
+
+    web_time::Instant::now()
+}
+
+/// Returns the time elapsed since the UNIX epoch.
+///
+/// `std::time::SystemTime::now` panics on `wasm32-unknown-unknown`, so this reads the clock through `web-time`,
+/// which uses the browser's clock there and the standard library everywhere else.
+pub fn unix_time() -> Duration {
+    web_time::SystemTime::now()
+        .duration_since(web_time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
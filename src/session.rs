@@ -0,0 +1,315 @@
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{ids::UserId, transport::HttpTransport, Error, Token, Victron};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// The kind of token stored in a [`Session`].
+pub enum TokenKind {
+    Bearer,
+    Access,
+}
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// A snapshot of a logged in [`Victron`] client, which can be stored and used to rebuild the client later
+/// without logging in again.
+///
+/// # Note
+/// The session contains the token in plain text, so treat it like a password.
+pub struct Session {
+    pub token_kind: TokenKind,
+    pub token: String,
+    /// The user id of the logged in user, if it was known when the snapshot was taken.
+    pub user_id: Option<UserId>,
+    /// The base URL of the VRM API.
+    pub base_url: String,
+    /// When the token expires, UNIX timestamp.
+    ///
+    /// This is read from the token itself, and is `None` for access tokens, which don't expire.
+    pub expires_at: Option<u64>,
+}
+
+impl Session {
+    #[must_use]
+    /// Returns true if the token has expired, or will within `leeway_secs` seconds.
+    pub fn is_expired(&self, leeway_secs: u64) -> bool {
+        let now = crate::runtime::unix_time().as_secs();
+
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= now.saturating_add(leeway_secs))
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("token_kind", &self.token_kind)
+            .field("token", &"..")
+            .field("user_id", &self.user_id)
+            .field("base_url", &self.base_url)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+impl<T: HttpTransport> Victron<T> {
+    /// Takes a snapshot of the current session, which can be restored with [`Victron::from_session`].
+    pub async fn session(&self) -> Session {
+        let (token_kind, token) = match &*self.token.read().await {
+            Token::Bearer(token) => (TokenKind::Bearer, token.clone()),
+            Token::Access(token) => (TokenKind::Access, token.clone()),
+        };
+
+        Session {
+            token_kind,
+            expires_at: (token_kind == TokenKind::Bearer)
+                .then(|| jwt_expiry(&token))
+                .flatten(),
+            token,
+            user_id: *self.user_id.read().await,
+            base_url: self.base_url.to_string(),
+        }
+    }
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl Victron {
+    #[must_use]
+    /// Rebuilds a client from a [`Session`].
+    ///
+    /// This does not check whether the session is still valid. Use [`Session::is_expired`] to check the expiry
+    /// beforehand, or [`Victron::with_credentials`] to log in again once VRM rejects the token.
+    /// See [`VictronBuilder::restore`](crate::VictronBuilder::restore) to use another transport.
+    pub fn from_session(session: Session) -> Self {
+        crate::VictronBuilder::new().restore(session)
+    }
+}
+
+/// Reads the `exp` claim from a JWT, without verifying it.
+fn jwt_expiry(token: &str) -> Option<u64> {
+    let payload = token.split('.').nth(1)?;
+    let claims: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).ok()?).ok()?;
+
+    claims.get("exp")?.as_u64()
+}
+
+#[derive(Debug, Clone)]
+/// Stores a [`Session`] as JSON in a file that only the current user can read.
+pub struct FileSessionStore {
+    path: PathBuf,
+}
+
+impl FileSessionStore {
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Loads the stored session, if there is one.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the file exists but could not be read.
+    /// - [`Error::Json`] if the file does not contain a valid session.
+    pub fn load(&self) -> Result<Option<Session>, Error> {
+        match fs::read(&self.path) {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Stores the session, replacing any previously stored one.
+    ///
+    /// On Unix the file is created with `0600` permissions.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the file could not be written.
+    /// - [`Error::Json`] if the session could not be serialized.
+    pub fn save(&self, session: &Session) -> Result<(), Error> {
+        let contents = serde_json::to_vec_pretty(session)?;
+
+        // Write to a temporary file first, so a crash never leaves a half-written session behind.
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            options.mode(0o600);
+
+            // `mode` only applies to newly created files, so tighten a leftover temporary file as well.
+            if temp_path.exists() {
+                fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(&temp_path)?;
+        file.write_all(&contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::rename(&temp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Removes the stored session, if there is one.
+    ///
+    /// # Errors
+    /// - [`Error::Io`] if the file exists but could not be removed.
+    pub fn clear(&self) -> Result<(), Error> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A directory for the files of one test, removed when the test ends.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("victron-energy-api-{name}-{}", std::process::id()));
+            fs::create_dir_all(&path).expect("temporary directory");
+
+            Self(path)
+        }
+
+        fn store(&self) -> (FileSessionStore, PathBuf) {
+            let path = self.0.join("session.json");
+
+            (FileSessionStore::new(&path), path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn session() -> Session {
+        Session {
+            token_kind: TokenKind::Bearer,
+            token: jwt(r#"{"uid": 22, "exp": 1718794800}"#),
+            user_id: Some(UserId(22)),
+            base_url: "https://vrmapi.victronenergy.com/v2".to_string(),
+            expires_at: Some(1_718_794_800),
+        }
+    }
+
+    fn jwt(claims: &str) -> String {
+        format!(
+            "{}.{}.signature",
+            URL_SAFE_NO_PAD.encode(r#"{"alg": "HS256", "typ": "JWT"}"#),
+            URL_SAFE_NO_PAD.encode(claims)
+        )
+    }
+
+    #[cfg(unix)]
+    fn mode(path: &std::path::Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::metadata(path)
+            .expect("stored session")
+            .permissions()
+            .mode()
+            & 0o777
+    }
+
+    #[test]
+    fn saves_and_loads() {
+        let dir = TempDir::new("saves-and-loads");
+        let (store, path) = dir.store();
+
+        store.save(&session()).expect("saved session");
+        assert_eq!(store.load().expect("stored session"), Some(session()));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        let replacement = Session {
+            token_kind: TokenKind::Access,
+            expires_at: None,
+            ..session()
+        };
+        store.save(&replacement).expect("saved session");
+        assert_eq!(store.load().expect("stored session"), Some(replacement));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn only_the_owner_can_read_the_session() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("owner-only");
+        let (store, path) = dir.store();
+
+        store.save(&session()).expect("saved session");
+        assert_eq!(mode(&path), 0o600);
+
+        // A temporary file left behind by a crash may have been created with other permissions.
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, "{").expect("temporary file");
+        fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o644)).expect("permissions");
+
+        store.save(&session()).expect("saved session");
+        assert_eq!(mode(&path), 0o600);
+    }
+
+    #[test]
+    fn missing_session() {
+        let dir = TempDir::new("missing");
+        let (store, _) = dir.store();
+
+        assert_eq!(store.load().expect("no session"), None);
+        store.clear().expect("nothing to clear");
+
+        store.save(&session()).expect("saved session");
+        store.clear().expect("cleared session");
+        assert_eq!(store.load().expect("no session"), None);
+    }
+
+    #[test]
+    fn invalid_session() {
+        let dir = TempDir::new("invalid");
+        let (store, path) = dir.store();
+
+        fs::write(&path, "not a session").expect("session file");
+        assert!(matches!(store.load(), Err(Error::Json(_))));
+
+        // A directory can't be read as a file, which isn't the same as there being no session.
+        let store = FileSessionStore::new(&dir.0);
+        assert!(matches!(store.load(), Err(Error::Io(_))));
+    }
+
+    #[test]
+    fn reads_the_expiry_of_a_jwt() {
+        assert_eq!(
+            jwt_expiry(&jwt(r#"{"uid": 22, "exp": 1718794800}"#)),
+            Some(1_718_794_800)
+        );
+        assert_eq!(jwt_expiry(&jwt(r#"{"uid": 22}"#)), None);
+        assert_eq!(jwt_expiry(&jwt(r#"{"exp": "soon"}"#)), None);
+        assert_eq!(jwt_expiry(&jwt("not json")), None);
+        assert_eq!(jwt_expiry("header.not base64!.signature"), None);
+        assert_eq!(jwt_expiry("an access token"), None);
+    }
+}
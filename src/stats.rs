@@ -0,0 +1,319 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{de, ser::SerializeSeq, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::{
+    datetime::{FromTimestamp, TimeRange},
+    endpoint::Endpoint,
+    ids::SiteId,
+    query::Query,
+    transport::HttpTransport,
+    Error, Method, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
+    /// Retrieves statistics of an installation, such as the battery state of charge or the energy produced, over
+    /// a time range.
+    ///
+    /// # Errors
+    /// - [`Error::Reqwest`] if there was an error sending the request.
+    /// - [`Error::Victron`] if the request failed.
+    pub async fn get_stats(&self, site_id: SiteId, request: &StatsRequest) -> Result<Stats, Error> {
+        let endpoint = StatsEndpoint { site_id, request };
+
+        let success = self.execute(&endpoint).await?;
+
+        Ok(Stats {
+            records: success.records,
+            totals: success.totals,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// What to request from [`Victron::get_stats`].
+pub struct StatsRequest {
+    kind: StatsKind,
+    interval: Interval,
+    range: Option<TimeRange>,
+    attribute_codes: Vec<String>,
+}
+
+impl StatsRequest {
+    #[must_use]
+    /// Requests statistics of the given kind, per hour, for VRM's default time range.
+    pub const fn new(kind: StatsKind) -> Self {
+        Self {
+            kind,
+            interval: Interval::Hours,
+            range: None,
+            attribute_codes: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    /// Sets how long each data point covers.
+    pub const fn interval(mut self, interval: Interval) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    #[must_use]
+    /// Sets the time range to request statistics for.
+    pub const fn range(mut self, range: TimeRange) -> Self {
+        self.range = Some(range);
+        self
+    }
+
+    #[must_use]
+    /// Adds an attribute code to request, such as [`crate::installations::codes::BATTERY_SOC`].
+    ///
+    /// This is required for [`StatsKind::Custom`].
+    pub fn attribute_code(mut self, code: &str) -> Self {
+        self.attribute_codes.push(code.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The kind of statistics to request.
+pub enum StatsKind {
+    /// The values of the most important attributes, such as battery state of charge and voltage.
+    Venus,
+    /// The power flows shown in the live feed of the VRM dashboard.
+    LiveFeed,
+    /// Energy consumption, split by where the energy came from.
+    Consumption,
+    /// Energy flows between solar, battery, grid and consumers, in kWh.
+    Kwh,
+    /// Energy produced by solar.
+    SolarYield,
+    /// Forecasts of solar yield and consumption.
+    Forecast,
+    /// The attributes given with [`StatsRequest::attribute_code`].
+    Custom,
+}
+
+impl StatsKind {
+    #[must_use]
+    /// Returns the value VRM expects in the `type` parameter.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Venus => "venus",
+            Self::LiveFeed => "live_feed",
+            Self::Consumption => "consumption",
+            Self::Kwh => "kwh",
+            Self::SolarYield => "solar_yield",
+            Self::Forecast => "forecast",
+            Self::Custom => "custom",
+        }
+    }
+}
+
+impl fmt::Display for StatsKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// How long each data point covers.
+pub enum Interval {
+    FifteenMinutes,
+    Hours,
+    TwoHours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl Interval {
+    #[must_use]
+    /// Returns the value VRM expects in the `interval` parameter.
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::FifteenMinutes => "15mins",
+            Self::Hours => "hours",
+            Self::TwoHours => "2hours",
+            Self::Days => "days",
+            Self::Weeks => "weeks",
+            Self::Months => "months",
+            Self::Years => "years",
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+struct StatsEndpoint<'a> {
+    site_id: SiteId,
+    request: &'a StatsRequest,
+}
+
+impl Endpoint for StatsEndpoint<'_> {
+    type Response = StatsSuccess;
+
+    const METHOD: Method = Method::GET;
+
+    fn path(&self) -> String {
+        format!("/installations/{}/stats", self.site_id)
+    }
+
+    fn query(&self) -> Query {
+        let query = Query::new()
+            .param("type", self.request.kind)
+            .param("interval", self.request.interval);
+
+        let query = match self.request.range {
+            Some(range) => query.range(range),
+            None => query,
+        };
+
+        self.request
+            .attribute_codes
+            .iter()
+            .fold(query, |query, code| query.param("attributeCodes[]", code))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsSuccess {
+    pub success: bool,
+    #[serde(default, deserialize_with = "deserialize_records")]
+    pub records: BTreeMap<String, Vec<StatsPoint>>,
+    #[serde(default, deserialize_with = "deserialize_totals")]
+    pub totals: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Statistics of an installation, as returned by [`Victron::get_stats`].
+pub struct Stats {
+    /// The data points of every attribute, keyed by attribute code, oldest first.
+    pub records: BTreeMap<String, Vec<StatsPoint>>,
+    /// The total of every attribute over the whole time range, keyed by attribute code.
+    pub totals: BTreeMap<String, f64>,
+}
+
+impl Stats {
+    #[must_use]
+    /// Returns the data points of an attribute, or an empty slice if VRM had no data for it.
+    pub fn series(&self, code: &str) -> &[StatsPoint] {
+        self.records.get(code).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+/// A single data point of a statistic.
+///
+/// VRM sends these as `[timestamp, value]`, or `[timestamp, mean, min, max]` for attributes that are averaged
+/// over the interval.
+pub struct StatsPoint {
+    /// The start of the interval, as a UNIX timestamp in milliseconds.
+    pub timestamp_ms: i64,
+    /// The value, or the mean value over the interval.
+    pub value: f64,
+    /// The lowest value during the interval, if VRM sent it.
+    pub min: Option<f64>,
+    /// The highest value during the interval, if VRM sent it.
+    pub max: Option<f64>,
+}
+
+impl StatsPoint {
+    #[must_use]
+    /// Returns the start of the interval, in UTC.
+    pub fn timestamp_at<T: FromTimestamp>(&self) -> Option<T> {
+        T::from_timestamp(self.timestamp_ms.div_euclid(1000), 0)
+    }
+}
+
+impl<'de> Deserialize<'de> for StatsPoint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let values = Vec::<Value>::deserialize(deserializer)?;
+
+        let timestamp_ms = values
+            .first()
+            .and_then(Value::as_i64)
+            .ok_or_else(|| de::Error::custom("expected a timestamp in milliseconds"))?;
+        let value = values
+            .get(1)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| de::Error::custom("expected a value"))?;
+
+        Ok(Self {
+            timestamp_ms,
+            value,
+            min: values.get(2).and_then(Value::as_f64),
+            max: values.get(3).and_then(Value::as_f64),
+        })
+    }
+}
+
+impl Serialize for StatsPoint {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let len = if self.min.is_some() || self.max.is_some() {
+            4
+        } else {
+            2
+        };
+
+        let mut seq = serializer.serialize_seq(Some(len))?;
+        seq.serialize_element(&self.timestamp_ms)?;
+        seq.serialize_element(&self.value)?;
+
+        if len == 4 {
+            seq.serialize_element(&self.min)?;
+            seq.serialize_element(&self.max)?;
+        }
+
+        seq.end()
+    }
+}
+
+/// Reads the data points of every attribute. VRM sends `false` instead of a list for attributes without data.
+fn deserialize_records<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Vec<StatsPoint>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Value::Object(records) = Value::deserialize(deserializer)? else {
+        return Ok(BTreeMap::new());
+    };
+
+    records
+        .into_iter()
+        .filter(|(_, points)| points.is_array())
+        .map(|(code, points)| {
+            serde_json::from_value(points)
+                .map(|points| (code, points))
+                .map_err(de::Error::custom)
+        })
+        .collect()
+}
+
+/// Reads the totals of every attribute, skipping the ones VRM sends as `false` because there was no data.
+fn deserialize_totals<'de, D>(deserializer: D) -> Result<BTreeMap<String, f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let Value::Object(totals) = Value::deserialize(deserializer)? else {
+        return Ok(BTreeMap::new());
+    };
+
+    Ok(totals
+        .into_iter()
+        .filter_map(|(code, total)| Some((code, total.as_f64()?)))
+        .collect())
+}
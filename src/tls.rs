@@ -0,0 +1,297 @@
+//! TLS options for the connections the client makes, such as trusting the root certificate of a network that
+//! inspects TLS traffic.
+//!
+//! These are available with the `rustls` or `native-tls` feature. If both are enabled, native-tls is used.
+//! The MQTT client of the `mqtt` feature always uses rustls, with the options of the client it was connected from,
+//! unless it was given its own.
+//!
+//! ```no_run
+//! use victron_energy_api::{tls::{Certificate, TlsConfig}, VictronBuilder};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # async fn run() -> Result<(), victron_energy_api::Error> {
+//! let tls = TlsConfig::new().add_root_certificate(Certificate::from_pem(&std::fs::read("corporate-ca.pem")?));
+//!
+//! let victron = VictronBuilder::new()
+//!     .tls(&tls)?
+//!     .login_access_token("me@example.com", "token")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+#[cfg(feature = "mqtt")]
+use std::{io, sync::Arc};
+
+#[cfg(feature = "mqtt")]
+use rumqttc::tokio_rustls::rustls::{
+    pki_types::{CertificateDer, PrivateKeyDer},
+    ClientConfig, RootCertStore,
+};
+
+use crate::Error;
+
+#[derive(Debug, Clone)]
+/// TLS options applied to every connection made by a client.
+pub struct TlsConfig {
+    root_certificates: Vec<Certificate>,
+    identity: Option<Identity>,
+    built_in_roots: bool,
+}
+
+impl TlsConfig {
+    #[must_use]
+    /// Creates options that trust the built-in root certificates, and nothing else.
+    pub const fn new() -> Self {
+        Self {
+            root_certificates: Vec::new(),
+            identity: None,
+            built_in_roots: true,
+        }
+    }
+
+    #[must_use]
+    /// Trusts an additional root certificate, such as the one used by a proxy that inspects TLS traffic.
+    pub fn add_root_certificate(mut self, certificate: Certificate) -> Self {
+        self.root_certificates.push(certificate);
+        self
+    }
+
+    #[must_use]
+    /// Presents a client certificate to servers that ask for one.
+    pub fn identity(mut self, identity: Identity) -> Self {
+        self.identity = Some(identity);
+        self
+    }
+
+    #[must_use]
+    /// Sets whether the built-in root certificates are trusted, in addition to the ones added with
+    /// [`TlsConfig::add_root_certificate`]. Defaults to true.
+    pub const fn built_in_roots(mut self, enabled: bool) -> Self {
+        self.built_in_roots = enabled;
+        self
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    /// Applies these options to a [`reqwest::ClientBuilder`].
+    pub(crate) fn apply(
+        &self,
+        mut builder: reqwest::ClientBuilder,
+    ) -> Result<reqwest::ClientBuilder, Error> {
+        builder = builder.tls_built_in_root_certs(self.built_in_roots);
+
+        for certificate in &self.root_certificates {
+            for certificate in certificate.to_reqwest()? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        if let Some(identity) = &self.identity {
+            builder = builder.identity(identity.to_reqwest()?);
+        }
+
+        Ok(builder)
+    }
+
+    #[cfg(feature = "mqtt")]
+    /// Builds a rustls configuration from these options, for the MQTT client.
+    pub(crate) fn to_rustls(&self) -> Result<Arc<ClientConfig>, Error> {
+        let mut roots = RootCertStore::empty();
+
+        if self.built_in_roots {
+            roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        }
+
+        for certificate in &self.root_certificates {
+            for certificate in certificate.to_rustls()? {
+                roots.add(certificate).map_err(invalid_data)?;
+            }
+        }
+
+        let builder = ClientConfig::builder().with_root_certificates(roots);
+
+        let config = match &self.identity {
+            Some(identity) => {
+                let (certificate_chain, key) = identity.to_rustls()?;
+
+                builder
+                    .with_client_auth_cert(certificate_chain, key)
+                    .map_err(invalid_data)?
+            }
+            None => builder.with_no_client_auth(),
+        };
+
+        Ok(Arc::new(config))
+    }
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone)]
+/// A root certificate to trust.
+pub struct Certificate {
+    encoding: Encoding,
+}
+
+#[derive(Clone)]
+enum Encoding {
+    Pem(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+impl Certificate {
+    #[must_use]
+    /// Reads one or more PEM encoded certificates, such as a CA bundle.
+    ///
+    /// The certificates are parsed when the client is built.
+    pub fn from_pem(pem: &[u8]) -> Self {
+        Self {
+            encoding: Encoding::Pem(pem.to_vec()),
+        }
+    }
+
+    #[must_use]
+    /// Reads a DER encoded certificate.
+    ///
+    /// The certificate is parsed when the client is built.
+    pub fn from_der(der: &[u8]) -> Self {
+        Self {
+            encoding: Encoding::Der(der.to_vec()),
+        }
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    fn to_reqwest(&self) -> Result<Vec<reqwest::Certificate>, Error> {
+        Ok(match &self.encoding {
+            Encoding::Pem(pem) => reqwest::Certificate::from_pem_bundle(pem)?,
+            Encoding::Der(der) => vec![reqwest::Certificate::from_der(der)?],
+        })
+    }
+
+    #[cfg(feature = "mqtt")]
+    fn to_rustls(&self) -> Result<Vec<CertificateDer<'static>>, Error> {
+        Ok(match &self.encoding {
+            Encoding::Pem(pem) => {
+                rustls_pemfile::certs(&mut pem.as_slice()).collect::<Result<_, _>>()?
+            }
+            Encoding::Der(der) => vec![CertificateDer::from(der.clone())],
+        })
+    }
+}
+
+impl fmt::Debug for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.encoding {
+            Encoding::Pem(pem) => write!(f, "Certificate::Pem({} bytes)", pem.len()),
+            Encoding::Der(der) => write!(f, "Certificate::Der({} bytes)", der.len()),
+        }
+    }
+}
+
+#[derive(Clone)]
+/// A client certificate and its private key.
+pub struct Identity {
+    kind: IdentityKind,
+}
+
+#[derive(Clone)]
+enum IdentityKind {
+    Pem {
+        certificate_chain: Vec<u8>,
+        key: Vec<u8>,
+    },
+    #[cfg(feature = "native-tls")]
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+impl Identity {
+    #[must_use]
+    /// Reads a PEM encoded certificate chain and its PEM encoded private key.
+    ///
+    /// With native-tls, the key has to be in PKCS #8 format (`BEGIN PRIVATE KEY`).
+    pub fn from_pem(certificate_chain: &[u8], key: &[u8]) -> Self {
+        Self {
+            kind: IdentityKind::Pem {
+                certificate_chain: certificate_chain.to_vec(),
+                key: key.to_vec(),
+            },
+        }
+    }
+
+    #[cfg(feature = "native-tls")]
+    #[must_use]
+    /// Reads a DER encoded PKCS #12 archive, such as a `.pfx` file, protected by `password`.
+    pub fn from_pkcs12_der(der: &[u8], password: &str) -> Self {
+        Self {
+            kind: IdentityKind::Pkcs12 {
+                der: der.to_vec(),
+                password: password.to_string(),
+            },
+        }
+    }
+
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    fn to_reqwest(&self) -> Result<reqwest::Identity, Error> {
+        Ok(match &self.kind {
+            #[cfg(feature = "native-tls")]
+            IdentityKind::Pem {
+                certificate_chain,
+                key,
+            } => reqwest::Identity::from_pkcs8_pem(certificate_chain, key)?,
+            #[cfg(not(feature = "native-tls"))]
+            IdentityKind::Pem {
+                certificate_chain,
+                key,
+            } => reqwest::Identity::from_pem(&[key.as_slice(), certificate_chain].concat())?,
+            #[cfg(feature = "native-tls")]
+            IdentityKind::Pkcs12 { der, password } => {
+                reqwest::Identity::from_pkcs12_der(der, password)?
+            }
+        })
+    }
+}
+
+impl Identity {
+    #[cfg(feature = "mqtt")]
+    fn to_rustls(&self) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), Error> {
+        match &self.kind {
+            IdentityKind::Pem {
+                certificate_chain,
+                key,
+            } => {
+                let certificate_chain = rustls_pemfile::certs(&mut certificate_chain.as_slice())
+                    .collect::<Result<_, _>>()?;
+                let key = rustls_pemfile::private_key(&mut key.as_slice())?
+                    .ok_or_else(|| invalid_data("no private key found in PEM"))?;
+
+                Ok((certificate_chain, key))
+            }
+            #[cfg(feature = "native-tls")]
+            IdentityKind::Pkcs12 { .. } => Err(Error::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "PKCS #12 identities are only supported by native-tls, which the MQTT client doesn't use",
+            ))),
+        }
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            IdentityKind::Pem { .. } => f.write_str("Identity::Pem(..)"),
+            #[cfg(feature = "native-tls")]
+            IdentityKind::Pkcs12 { .. } => f.write_str("Identity::Pkcs12(..)"),
+        }
+    }
+}
+
+#[cfg(feature = "mqtt")]
+/// Reports certificates or keys that rustls rejected as invalid data.
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Error {
+    Error::Io(io::Error::new(io::ErrorKind::InvalidData, error))
+}
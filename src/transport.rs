@@ -0,0 +1,168 @@
+//! The HTTP layer the client sends its requests through.
+//!
+//! [`Victron`](crate::Victron) is generic over an [`HttpTransport`], and uses [`DefaultTransport`] by default,
+//! which is `ReqwestTransport` with the `tokio` feature. Implement the trait to use another HTTP stack or async
+//! runtime, add middleware such as request signing, or serve responses from memory in tests.
+
+use std::future::Future;
+
+use http::header::{ACCEPT, CONTENT_TYPE};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use url::Url;
+
+use crate::{query::Query, Error, Failure, Method};
+
+/// A request sent through an [`HttpTransport`].
+pub type HttpRequest = http::Request<Vec<u8>>;
+/// A response returned by an [`HttpTransport`].
+pub type HttpResponse = http::Response<Vec<u8>>;
+
+/// Sends HTTP requests on behalf of the client.
+///
+/// A transport only has to send the request and return the response, whatever its status. Authentication,
+/// rate limiting, retries and error handling are all done by the client.
+pub trait HttpTransport: std::fmt::Debug + Clone + Send + Sync + 'static {
+    /// Sends a request and returns the response.
+    ///
+    /// # Errors
+    /// Errors that prevented getting a response at all, such as connection failures. Transports other than
+    /// [`ReqwestTransport`] should use [`Error::Transport`].
+    fn send(
+        &self,
+        request: HttpRequest,
+    ) -> impl Future<Output = Result<HttpResponse, Error>> + MaybeSend;
+}
+
+/// [`Send`] on every target except `wasm32`, where futures backed by JavaScript promises can't be sent between
+/// threads, and there is only one thread anyway.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSend: Send {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send> MaybeSend for T {}
+
+/// [`Send`] on every target except `wasm32`, where futures backed by JavaScript promises can't be sent between
+/// threads, and there is only one thread anyway.
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSend {}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> MaybeSend for T {}
+
+/// The transport of a client unless another one is given: [`ReqwestTransport`] with the `tokio` feature or on
+/// wasm32, and `NoTransport` otherwise.
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+pub type DefaultTransport = ReqwestTransport;
+
+/// The transport of a client unless another one is given: `ReqwestTransport` with the `tokio` feature or on
+/// wasm32, and [`NoTransport`] otherwise.
+#[cfg(not(any(feature = "tokio", target_arch = "wasm32")))]
+pub type DefaultTransport = NoTransport;
+
+#[cfg(not(any(feature = "tokio", target_arch = "wasm32")))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The [`DefaultTransport`] without the `tokio` feature. It can't be created, so every client needs a transport
+/// for the async runtime it runs on.
+pub enum NoTransport {}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default)]
+/// An [`HttpTransport`] backed by a [`reqwest::Client`].
+///
+/// reqwest needs a tokio runtime to send requests, so this is only available with the `tokio` feature, or on
+/// wasm32, where requests go through the browser. On other runtimes, such as smol or async-std, implement
+/// [`HttpTransport`] with an HTTP client for that runtime instead.
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl ReqwestTransport {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    /// Uses an existing, already configured [`reqwest::Client`].
+    pub const fn from_client(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    #[must_use]
+    /// Returns the [`reqwest::Client`] requests are sent with.
+    pub const fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    #[cfg(all(
+        any(feature = "rustls", feature = "native-tls"),
+        not(target_arch = "wasm32")
+    ))]
+    /// Creates a transport whose client connects using the given TLS options.
+    ///
+    /// # Errors
+    /// - `Error::Reqwest` if a certificate or identity could not be read, or the client could not be built.
+    pub fn with_tls(tls: &crate::tls::TlsConfig) -> Result<Self, Error> {
+        Ok(Self::from_client(
+            tls.apply(reqwest::Client::builder())?.build()?,
+        ))
+    }
+}
+
+#[cfg(any(feature = "tokio", target_arch = "wasm32"))]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, Error> {
+        let resp = self
+            .client
+            .execute(reqwest::Request::try_from(request)?)
+            .await?;
+
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let body = resp.bytes().await?.to_vec();
+
+        let mut response = HttpResponse::new(body);
+        *response.status_mut() = status;
+        *response.headers_mut() = headers;
+
+        Ok(response)
+    }
+}
+
+/// Builds a request with the given query string and JSON body.
+pub(crate) fn build_request(
+    method: Method,
+    url: &str,
+    query: &Query,
+    body: Option<&Value>,
+) -> Result<HttpRequest, Error> {
+    let mut url = Url::parse(url)?;
+    if !query.is_empty() {
+        url.query_pairs_mut().extend_pairs(query.pairs());
+    }
+
+    let builder = http::Request::builder()
+        .method(method)
+        .uri(url.as_str())
+        .header(ACCEPT, "application/json");
+
+    let request = match body {
+        Some(body) => builder
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(body)?)?,
+        None => builder.body(Vec::new())?,
+    };
+
+    Ok(request)
+}
+
+/// Parses a successful response as JSON, or turns an unsuccessful one into a [`Failure`].
+pub(crate) fn parse_response<R: DeserializeOwned>(resp: &HttpResponse) -> Result<R, Error> {
+    if resp.status().is_success() {
+        return Ok(serde_json::from_slice(resp.body())?);
+    }
+
+    Err(Failure::from_response(resp).into())
+}
@@ -1,35 +1,28 @@
-use serde::Deserialize;
-use serde_json::json;
-
-use crate::{installations::Installation, Error, Failure, Victron, BASE_URL};
-
-impl Victron {
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    endpoint::Endpoint,
+    ids::{SiteId, UserId},
+    installations::Installation,
+    query::Query,
+    transport::HttpTransport,
+    Error, Method, Victron,
+};
+
+impl<T: HttpTransport> Victron<T> {
     /// Adds a new site to the user. An email will be sent to the user with a link when the procedure is complete.
     ///
     /// # Errors
     /// - [`Error::Reqwest`] if there was an error sending the request.
     /// - [`Error::Victron`] if the request failed.
-    pub async fn add_new_site(&self, identifier: &str) -> Result<String, Error> {
-        let resp = self
-            .client
-            .post(format!(
-                "{BASE_URL}/users/{}/addSite",
-                self.ensure_user_id().await?
-            ))
-            .header("x-authorization", self.token.to_string())
-            .json(&json!({ "siteIdentifier": identifier}))
-            .send()
-            .await?;
-
-        if resp.status().is_success() {
-            let success = resp.json::<AddNewSiteSuccess>().await?;
-
-            return Ok(success.records.site_id);
-        }
-
-        let failure = resp.json::<Failure>().await?;
-
-        Err(failure.into())
+    pub async fn add_new_site(&self, identifier: &str) -> Result<SiteId, Error> {
+        let endpoint = AddSiteEndpoint {
+            user_id: self.ensure_user_id().await?,
+            identifier,
+        };
+
+        Ok(self.execute(&endpoint).await?.records.site_id)
     }
 
     /// Retrieves a list of installations to which the user is connected. Normal users can only retrieve their own,
@@ -42,60 +35,39 @@ impl Victron {
         &self,
         extended: bool,
     ) -> Result<Vec<Installation>, Error> {
-        let resp = self
-            .client
-            .get(format!(
-                "{BASE_URL}/users/{}/installations",
-                self.ensure_user_id().await?
-            ))
-            .header("x-authorization", self.token.to_string())
-            .json(&json!({
-                "extended": i32::from(extended),
-            }))
-            .send()
-            .await?;
-
-        if resp.status().is_success() {
-            let success = resp.json::<InstallationSuccess>().await?;
-
-            return Ok(success.records);
-        }
-
-        let failure = resp.json::<Failure>().await?;
-
-        Err(failure.into())
+        let endpoint = InstallationsEndpoint {
+            user_id: self.ensure_user_id().await?,
+            extended,
+            site_id: None,
+        };
+
+        Ok(self.execute(&endpoint).await?.records)
     }
 
-    /// Retrieves a specific installation or site by its id. See [`get_all_installations_or_sites`] for more information.
+    /// Retrieves a specific installation or site by its id. See [`Victron::get_all_installations_or_sites`] for more
+    /// information.
     ///
     /// # Errors
     /// - [`Error::Reqwest`] if there was an error sending the request.
     /// - [`Error::Victron`] if the request failed.
+    /// - [`Error::InstallationNotFound`] if the user has no installation with this id.
     pub async fn get_installation_or_site(
         &self,
         extended: bool,
-        site_id: i32,
+        site_id: SiteId,
     ) -> Result<Installation, Error> {
-        let resp = self
-            .client
-            .get(format!(
-                "{BASE_URL}/users/{}/installations",
-                self.ensure_user_id().await?
-            ))
-            .header("x-authorization", self.token.to_string())
-            .json(&json!({ "extended": i32::from(extended), "siteId": site_id }))
-            .send()
-            .await?;
-
-        if resp.status().is_success() {
-            let success = resp.json::<InstallationSuccess>().await?;
-
-            return Ok(success.records[0].clone());
-        }
-
-        let failure = resp.json::<Failure>().await?;
-
-        Err(failure.into())
+        let endpoint = InstallationsEndpoint {
+            user_id: self.ensure_user_id().await?,
+            extended,
+            site_id: Some(site_id),
+        };
+
+        self.execute(&endpoint)
+            .await?
+            .records
+            .into_iter()
+            .find(|installation| installation.site_id == site_id)
+            .ok_or(Error::InstallationNotFound(site_id))
     }
 
     /// Retrieves id, name, email and country of the user that is currently logged in
@@ -104,52 +76,97 @@ impl Victron {
     /// - [`Error::Reqwest`] if there was an error sending the request.
     /// - [`Error::Victron`] if the request failed.
     pub async fn get_user_info(&self) -> Result<User, Error> {
-        let resp = self
-            .client
-            .get(format!("{BASE_URL}/users/me"))
-            .header("x-authorization", self.token.to_string())
-            .header("content-type", "application/json")
-            .send()
-            .await?;
+        Ok(self.execute(&UserInfoEndpoint).await?.user)
+    }
+}
+
+struct AddSiteEndpoint<'a> {
+    user_id: UserId,
+    identifier: &'a str,
+}
+
+impl Endpoint for AddSiteEndpoint<'_> {
+    type Response = AddNewSiteSuccess;
+
+    const METHOD: Method = Method::POST;
+
+    fn path(&self) -> String {
+        format!("/users/{}/addSite", self.user_id)
+    }
+
+    fn body(&self) -> Option<Value> {
+        Some(json!({ "siteIdentifier": self.identifier }))
+    }
+}
+
+struct InstallationsEndpoint {
+    user_id: UserId,
+    extended: bool,
+    site_id: Option<SiteId>,
+}
+
+impl Endpoint for InstallationsEndpoint {
+    type Response = InstallationSuccess;
+
+    const METHOD: Method = Method::GET;
+
+    fn path(&self) -> String {
+        format!("/users/{}/installations", self.user_id)
+    }
+
+    fn query(&self) -> Query {
+        Query::new()
+            .flag("extended", self.extended)
+            .optional("idSite", self.site_id)
+    }
+}
 
-        if resp.status().is_success() {
-            let success = resp.json::<UserSuccess>().await?;
+struct UserInfoEndpoint;
 
-            return Ok(success.user);
-        }
+impl Endpoint for UserInfoEndpoint {
+    type Response = UserSuccess;
 
-        let failure = resp.json::<Failure>().await?;
+    const METHOD: Method = Method::GET;
 
-        Err(failure.into())
+    fn path(&self) -> String {
+        "/users/me".to_string()
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserSuccess {
     pub success: bool,
     pub user: User,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
-    pub id: String,
+    pub id: UserId,
     pub name: String,
     pub email: String,
     pub country: String,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddNewSiteSuccess {
     pub success: bool,
     pub records: AddNewSite,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AddNewSite {
-    pub site_id: String,
+    pub site_id: SiteId,
+    #[cfg(feature = "unknown-fields")]
+    #[serde(flatten)]
+    /// Fields VRM sent that this library doesn't know about yet.
+    pub extra: serde_json::Map<String, Value>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallationSuccess {
     pub success: bool,
     pub records: Vec<Installation>,
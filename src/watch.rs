@@ -0,0 +1,410 @@
+//! Watching an installation for changes, without writing a polling loop.
+//!
+//! [`Victron::watch_installation`] polls an installation and yields what changed since the previous poll. Polls
+//! where nothing changed are skipped, and failed polls are retried with an increasing delay.
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use futures_util::StreamExt;
+//! use victron_energy_api::{ids::SiteId, installations::codes, Victron};
+//!
+//! # #[cfg(feature = "tokio")]
+//! # async fn run(site_id: SiteId) -> Result<(), victron_energy_api::Error> {
+//! let victron = Victron::login_access_token("me@example.com", "token").await?;
+//! let mut changes = Box::pin(victron.watch_installation(site_id, Duration::from_secs(60)));
+//!
+//! while let Some(changes) = changes.next().await {
+//!     let changes = changes?;
+//!
+//!     for alarm in &changes.new_alarms {
+//!         println!("New alarm: {alarm}");
+//!     }
+//!
+//!     for (key, soc) in &changes.attributes {
+//!         if key.code == codes::BATTERY_SOC {
+//!             println!("Battery {:?} state of charge: {:?} -> {:?}", key.instance, soc.previous, soc.current);
+//!         }
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{collections::BTreeMap, time::Duration};
+
+use futures_util::{stream, Stream};
+
+use crate::{
+    ids::SiteId,
+    installations::{AttributeValue, Installation},
+    runtime,
+    transport::HttpTransport,
+    Error, Victron,
+};
+
+/// The longest time to wait before polling again after failed polls, unless the polling interval is longer.
+pub const MAX_BACKOFF: Duration = Duration::from_mins(5);
+
+impl<T: HttpTransport> Victron<T> {
+    /// Polls an installation every `interval`, yielding what changed since the previous poll.
+    ///
+    /// The installation is requested with extended data, so changes to its attributes can be reported. The first
+    /// item describes the installation as it is, as a change from nothing. After that, polls where neither the
+    /// alarms, the last timestamp nor any attribute changed are skipped.
+    ///
+    /// A failed poll yields its error, and the stream keeps polling. The delay doubles after every failure in a
+    /// row, up to [`MAX_BACKOFF`], and returns to `interval` after a successful poll. Drop the stream to stop
+    /// watching.
+    pub fn watch_installation(
+        &self,
+        site_id: SiteId,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<InstallationChanges, Error>> {
+        let state = WatchState {
+            victron: self.clone(),
+            site_id,
+            interval,
+            previous: None,
+            delay: None,
+            failures: 0,
+        };
+
+        stream::unfold(state, |mut state| async move {
+            loop {
+                if let Some(delay) = state.delay {
+                    runtime::sleep(delay).await;
+                }
+
+                match state
+                    .victron
+                    .get_installation_or_site(true, state.site_id)
+                    .await
+                {
+                    Ok(installation) => {
+                        state.failures = 0;
+                        state.delay = Some(state.interval);
+
+                        let first = state.previous.is_none();
+                        let changes =
+                            InstallationChanges::between(state.previous.as_ref(), installation);
+                        state.previous = Some(changes.installation.clone());
+
+                        if first || !changes.is_empty() {
+                            return Some((Ok(changes), state));
+                        }
+                    }
+                    Err(error) => {
+                        state.failures = state.failures.saturating_add(1);
+                        state.delay = Some(backoff(state.interval, state.failures));
+
+                        return Some((Err(error), state));
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct WatchState<T> {
+    victron: Victron<T>,
+    site_id: SiteId,
+    interval: Duration,
+    /// The installation as it was at the last successful poll.
+    previous: Option<Installation>,
+    /// How long to wait before the next poll, or `None` before the first poll.
+    delay: Option<Duration>,
+    /// How many polls in a row have failed.
+    failures: u32,
+}
+
+/// Returns how long to wait after `failures` failed polls in a row.
+fn backoff(interval: Duration, failures: u32) -> Duration {
+    let factor = 1_u32 << failures.min(16);
+
+    interval
+        .saturating_mul(factor)
+        .min(MAX_BACKOFF.max(interval))
+}
+
+#[derive(Debug, Clone)]
+/// What changed in an installation between two polls of [`Victron::watch_installation`].
+pub struct InstallationChanges {
+    pub site_id: SiteId,
+    /// The installation as it is now.
+    pub installation: Installation,
+    /// The timestamp of the last data VRM received, if it changed.
+    pub last_timestamp: Option<Change<Option<i64>>>,
+    /// Alarms that are active now, but weren't before.
+    pub new_alarms: Vec<String>,
+    /// Alarms that were active before, but aren't anymore.
+    pub cleared_alarms: Vec<String>,
+    /// The attributes whose value changed.
+    ///
+    /// An attribute that appeared has no previous value, and one that disappeared has no current value.
+    pub attributes: BTreeMap<AttributeKey, Change<Option<AttributeValue>>>,
+}
+
+impl InstallationChanges {
+    #[must_use]
+    /// Compares an installation with how it was before. Without a previous installation, everything in `current`
+    /// is reported as new.
+    pub fn between(previous: Option<&Installation>, current: Installation) -> Self {
+        let previous_timestamp = previous.and_then(|previous| previous.last_timestamp);
+        let last_timestamp = (previous_timestamp != current.last_timestamp).then_some(Change {
+            previous: previous_timestamp,
+            current: current.last_timestamp,
+        });
+
+        let previous_alarms = previous.map_or(&[][..], alarms);
+        let current_alarms = alarms(&current);
+
+        let new_alarms = current_alarms
+            .iter()
+            .filter(|alarm| !previous_alarms.contains(alarm))
+            .cloned()
+            .collect();
+        let cleared_alarms = previous_alarms
+            .iter()
+            .filter(|alarm| !current_alarms.contains(alarm))
+            .cloned()
+            .collect();
+
+        let previous_values = previous.map(attribute_values).unwrap_or_default();
+        let mut current_values = attribute_values(&current);
+
+        let mut attributes = BTreeMap::new();
+
+        for (key, previous) in previous_values {
+            let current = current_values.remove(&key).flatten();
+
+            if previous != current {
+                attributes.insert(key, Change { previous, current });
+            }
+        }
+
+        for (key, current) in current_values
+            .into_iter()
+            .filter(|(_, value)| value.is_some())
+        {
+            attributes.insert(
+                key,
+                Change {
+                    previous: None,
+                    current,
+                },
+            );
+        }
+
+        Self {
+            site_id: current.site_id,
+            installation: current,
+            last_timestamp,
+            new_alarms,
+            cleared_alarms,
+            attributes,
+        }
+    }
+
+    #[must_use]
+    /// True if nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.last_timestamp.is_none()
+            && self.new_alarms.is_empty()
+            && self.cleared_alarms.is_empty()
+            && self.attributes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Which attribute of an installation changed. See [`InstallationChanges::attributes`].
+pub struct AttributeKey {
+    /// The code of the attribute, such as [`crate::installations::codes::BATTERY_SOC`].
+    pub code: String,
+    /// The device instance of a data attribute, to tell apart devices of the same type. `None` for summaries.
+    pub instance: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A value before and after a change.
+pub struct Change<V> {
+    pub previous: V,
+    pub current: V,
+}
+
+fn alarms(installation: &Installation) -> &[String] {
+    installation.current_alarms.as_deref().unwrap_or_default()
+}
+
+fn attribute_values(installation: &Installation) -> BTreeMap<AttributeKey, Option<AttributeValue>> {
+    installation
+        .extended
+        .iter()
+        .flatten()
+        .map(|extended| {
+            let key = AttributeKey {
+                code: extended.code().to_string(),
+                instance: extended.instance().map(str::to_string),
+            };
+
+            (key, extended.value())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn installation(edit: impl FnOnce(&mut Value)) -> Installation {
+        let mut installations: Vec<Value> =
+            serde_json::from_str(include_str!("mock/installations.json")).expect("valid fixture");
+        let mut installation = installations.swap_remove(0);
+        edit(&mut installation);
+
+        serde_json::from_value(installation).expect("valid installation")
+    }
+
+    fn key(code: &str, instance: Option<&str>) -> AttributeKey {
+        AttributeKey {
+            code: code.to_string(),
+            instance: instance.map(str::to_string),
+        }
+    }
+
+    /// Sets the raw value of the data attribute with `code` and `instance`.
+    fn set_value(installation: &mut Value, code: &str, instance: &str, value: &str) {
+        let attribute = installation["extended"]
+            .as_array_mut()
+            .and_then(|extended| {
+                extended.iter_mut().find(|attribute| {
+                    attribute["code"] == code && attribute["instance"] == instance
+                })
+            })
+            .expect("attribute in fixture");
+
+        attribute["rawValue"] = json!(value);
+    }
+
+    #[test]
+    fn without_previous_everything_is_new() {
+        let changes = InstallationChanges::between(None, installation(|_| {}));
+
+        assert!(!changes.is_empty());
+        assert_eq!(
+            changes.last_timestamp,
+            Some(Change {
+                previous: None,
+                current: Some(1_718_791_200)
+            })
+        );
+        assert_eq!(changes.attributes.len(), 5);
+        assert!(changes
+            .attributes
+            .values()
+            .all(|change| change.previous.is_none() && change.current.is_some()));
+    }
+
+    #[test]
+    fn unchanged_installation_is_empty() {
+        let previous = installation(|_| {});
+        let changes = InstallationChanges::between(Some(&previous), installation(|_| {}));
+
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn reports_alarms_and_timestamp() {
+        let previous = installation(|installation| {
+            installation["current_alarms"] = json!(["Low battery", "High temperature"]);
+        });
+        let current = installation(|installation| {
+            installation["current_alarms"] = json!(["High temperature", "Overload"]);
+            installation["last_timestamp"] = json!(1_718_791_500);
+        });
+
+        let changes = InstallationChanges::between(Some(&previous), current);
+
+        assert_eq!(changes.new_alarms, ["Overload"]);
+        assert_eq!(changes.cleared_alarms, ["Low battery"]);
+        assert_eq!(
+            changes.last_timestamp,
+            Some(Change {
+                previous: Some(1_718_791_200),
+                current: Some(1_718_791_500)
+            })
+        );
+        assert!(changes.attributes.is_empty());
+    }
+
+    #[test]
+    fn tells_instances_apart() {
+        let second_battery = |installation: &mut Value| {
+            let mut battery = installation["extended"][0].clone();
+            battery["instance"] = json!("513");
+            battery["rawValue"] = json!("40");
+            installation["extended"]
+                .as_array_mut()
+                .expect("extended in fixture")
+                .push(battery);
+        };
+
+        let previous = installation(second_battery);
+        let current = installation(|installation| {
+            second_battery(installation);
+            set_value(installation, "bs", "513", "41");
+        });
+
+        let changes = InstallationChanges::between(Some(&previous), current);
+
+        assert_eq!(changes.attributes.len(), 1);
+        assert_eq!(
+            changes.attributes[&key("bs", Some("513"))],
+            Change {
+                previous: Some(AttributeValue::Numeric(40.0)),
+                current: Some(AttributeValue::Numeric(41.0))
+            }
+        );
+    }
+
+    #[test]
+    fn reports_attributes_that_disappeared() {
+        let previous = installation(|_| {});
+        let current = installation(|installation| {
+            installation["extended"]
+                .as_array_mut()
+                .expect("extended in fixture")
+                .retain(|attribute| attribute["code"] != "consumption");
+        });
+
+        let changes = InstallationChanges::between(Some(&previous), current);
+
+        assert_eq!(
+            changes.attributes[&key("consumption", None)],
+            Change {
+                previous: Some(AttributeValue::Numeric(412.0)),
+                current: None
+            }
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let interval = Duration::from_secs(10);
+
+        assert_eq!(backoff(interval, 1), Duration::from_secs(20));
+        assert_eq!(backoff(interval, 2), Duration::from_secs(40));
+        assert_eq!(backoff(interval, 5), MAX_BACKOFF);
+        assert_eq!(backoff(interval, u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn backoff_never_shortens_long_intervals() {
+        let interval = Duration::from_mins(10);
+
+        assert_eq!(backoff(interval, 1), interval);
+        assert_eq!(backoff(interval, u32::MAX), interval);
+    }
+}
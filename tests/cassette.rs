@@ -0,0 +1,106 @@
+use std::{env, fs, path::PathBuf, process};
+
+use victron_energy_api::{
+    cassette::{Cassette, CassetteMode},
+    ids::{SiteId, UserId},
+    mock::{self, MockFailure, MockVrm},
+    Token, VictronBuilder,
+};
+
+const REQUESTS: usize = 32;
+
+fn cassette_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("victron-cassette-{}-{name}.json", process::id()))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn records_and_replays_concurrent_requests() {
+    let path = cassette_path("concurrent");
+    let site_ids = [SiteId(151_734), SiteId(151_735)];
+
+    let cassette = Cassette::record(MockVrm::new(), &path).unwrap();
+    let victron = VictronBuilder::with_transport(cassette.clone())
+        .base_url(mock::BASE_URL)
+        .login("john@example.com", "password", None, false)
+        .await
+        .unwrap();
+
+    let tasks: Vec<_> = (0..REQUESTS)
+        .map(|i| {
+            let victron = victron.clone();
+            tokio::spawn(async move {
+                victron
+                    .get_installation_or_site(true, site_ids[i % site_ids.len()])
+                    .await
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let replayed = Cassette::replay(&path).unwrap();
+    assert_eq!(replayed.mode(), CassetteMode::Replay);
+    assert_eq!(replayed.interactions(), cassette.interactions());
+    assert_eq!(replayed.interactions().len(), REQUESTS + 1);
+
+    let victron = VictronBuilder::with_transport(replayed)
+        .base_url(mock::BASE_URL)
+        .with_token(Token::Bearer("replayed".to_string()), Some(UserId(22)));
+
+    let tasks: Vec<_> = (0..REQUESTS)
+        .map(|i| {
+            let victron = victron.clone();
+            let site_id = site_ids[i % site_ids.len()];
+            tokio::spawn(async move {
+                let installation = victron.get_installation_or_site(true, site_id).await?;
+                assert_eq!(installation.site_id, site_id);
+                Ok::<_, victron_energy_api::Error>(())
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    assert!(victron
+        .get_installation_or_site(true, site_ids[0])
+        .await
+        .is_err());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[tokio::test]
+async fn redacts_secrets() {
+    let path = cassette_path("redacted");
+    let vrm = MockVrm::new();
+
+    let cassette = Cassette::record(vrm.clone(), &path).unwrap();
+    let victron = VictronBuilder::with_transport(cassette)
+        .base_url(mock::BASE_URL)
+        .login("john@example.com", "hunter2", None, false)
+        .await
+        .unwrap();
+
+    vrm.fail_next(MockFailure::Response {
+        status: 200,
+        body: "access_token=secret-token&expires=3600".to_string(),
+    });
+    assert!(victron.get_user_info().await.is_err());
+
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    for secret in [
+        "john@example.com",
+        "hunter2",
+        "mock-token-1",
+        "secret-token",
+    ] {
+        assert!(!contents.contains(secret), "{secret} was not redacted");
+    }
+    assert!(contents.contains("access_token=REDACTED&expires=3600"));
+}
@@ -0,0 +1,57 @@
+//! Drives the client without a tokio runtime, as on smol or async-std, even when the `tokio` feature is enabled.
+
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+use victron_energy_api::{mock::MockVrm, rate_limit::RateLimit};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// A minimal executor that polls `future` on the current thread, parking it while the future is pending.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return output;
+        }
+
+        thread::park();
+    }
+}
+
+#[test]
+fn rate_limited_requests_without_tokio() {
+    let vrm = MockVrm::new();
+
+    block_on(async {
+        let victron = vrm
+            .login()
+            .await
+            .unwrap()
+            .with_rate_limit(RateLimit::new(1, 20.0));
+
+        let started = Instant::now();
+        victron.get_user_info().await.unwrap();
+        victron.get_user_info().await.unwrap();
+
+        // The second request had to wait for the bucket to refill.
+        assert!(started.elapsed() >= Duration::from_millis(40));
+    });
+
+    assert_eq!(vrm.requests().len(), 3);
+}
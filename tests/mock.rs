@@ -0,0 +1,425 @@
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use serde_json::json;
+
+use victron_energy_api::{
+    credentials::Credentials,
+    ids::SiteId,
+    login::{LoginOutcome, VerificationMode},
+    mock::{MockFailure, MockVrm},
+    Error, ErrorCode, Failure, Method, Token, Victron,
+};
+
+fn password() -> Credentials {
+    Credentials::Password {
+        username: "john@example.com".to_string(),
+        password: "password".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn logs_in_again_when_the_token_expires() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap().with_credentials(password());
+
+    vrm.expire_tokens();
+    vrm.clear_requests();
+
+    let user = victron.get_user_info().await.unwrap();
+    assert_eq!(user.email, "john@example.com");
+
+    let requests = vrm.requests();
+    let paths: Vec<_> = requests
+        .iter()
+        .map(|request| request.path.as_str())
+        .collect();
+    assert_eq!(paths, ["/users/me", "/auth/login", "/users/me"]);
+    assert_eq!(
+        requests[2].headers["x-authorization"],
+        "Bearer mock-token-2"
+    );
+}
+
+#[tokio::test]
+async fn retries_a_rejected_request_only_once() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap().with_credentials(password());
+
+    vrm.fail_next_on("/users/me", MockFailure::Unauthorized);
+    vrm.fail_next_on("/users/me", MockFailure::Unauthorized);
+
+    let Err(Error::Victron(failure)) = victron.get_user_info().await else {
+        panic!("expected the second 401 to be returned");
+    };
+    assert_eq!(failure.status, 401);
+    assert_eq!(failure.code, ErrorCode::TokenExpired);
+}
+
+#[tokio::test]
+async fn expired_token_without_credentials_fails() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    vrm.expire_tokens();
+    vrm.clear_requests();
+
+    let Err(Error::Victron(failure)) = victron.get_user_info().await else {
+        panic!("expected the 401 to be returned");
+    };
+    assert_eq!(failure.code, ErrorCode::TokenExpired);
+    assert_eq!(vrm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn clones_share_a_refreshed_token() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap().with_credentials(password());
+    let clone = victron.clone();
+
+    vrm.expire_tokens();
+    victron.get_user_info().await.unwrap();
+    vrm.clear_requests();
+
+    clone.get_user_info().await.unwrap();
+    assert_eq!(vrm.requests().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_rejections_log_in_once() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap().with_credentials(password());
+
+    vrm.expire_tokens();
+    vrm.clear_requests();
+
+    let tasks: Vec<_> = (0..16)
+        .map(|_| {
+            let victron = victron.clone();
+            tokio::spawn(async move { victron.get_user_info().await })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let logins = vrm
+        .requests()
+        .iter()
+        .filter(|request| request.path == "/auth/login")
+        .count();
+    assert_eq!(logins, 1);
+}
+
+#[tokio::test]
+async fn callback_can_use_the_client() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    let callback = {
+        let vrm = vrm.clone();
+        let victron = victron.clone();
+
+        Credentials::callback(move || {
+            let vrm = vrm.clone();
+            let victron = victron.clone();
+
+            async move {
+                assert_eq!(victron.session().await.token, "mock-token-1");
+                Ok(Token::Bearer(vrm.login().await?.session().await.token))
+            }
+        })
+    };
+    let victron = victron.with_credentials(callback);
+
+    vrm.expire_tokens();
+
+    let user = tokio::time::timeout(Duration::from_secs(5), victron.get_user_info())
+        .await
+        .expect("the callback should not deadlock")
+        .unwrap();
+    assert_eq!(user.email, "john@example.com");
+    assert_eq!(victron.session().await.token, "mock-token-2");
+}
+
+#[tokio::test]
+async fn rejected_requests_of_the_callback_fail() {
+    let vrm = MockVrm::new();
+    // The callback uses the client it belongs to, which only exists once the callback does.
+    let client = Arc::new(OnceLock::<Victron<MockVrm>>::new());
+
+    let callback = {
+        let vrm = vrm.clone();
+        let client = Arc::clone(&client);
+
+        Credentials::callback(move || {
+            let vrm = vrm.clone();
+            let client = Arc::clone(&client);
+
+            async move {
+                let Err(Error::Victron(failure)) = client.get().unwrap().get_user_info().await
+                else {
+                    panic!("expected the request of the callback to be rejected");
+                };
+                assert_eq!(failure.status, 401);
+
+                Ok(Token::Bearer(vrm.login().await?.session().await.token))
+            }
+        })
+    };
+    let victron = vrm.login().await.unwrap().with_credentials(callback);
+    client.set(victron.clone()).unwrap();
+
+    vrm.expire_tokens();
+
+    let user = tokio::time::timeout(Duration::from_secs(5), victron.get_user_info())
+        .await
+        .expect("the callback should not wait for itself")
+        .unwrap();
+    assert_eq!(user.email, "john@example.com");
+    assert_eq!(victron.session().await.token, "mock-token-2");
+}
+
+#[tokio::test]
+async fn restores_a_session_of_the_mock() {
+    let vrm = MockVrm::new();
+    let session = vrm.login().await.unwrap().session().await;
+    vrm.clear_requests();
+
+    let victron = vrm.builder().restore(session);
+    victron.get_user_info().await.unwrap();
+
+    assert_eq!(vrm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn two_step_login() {
+    let vrm = MockVrm::new();
+    vrm.require_verification("123456");
+
+    let Err(Error::VerificationRequired(VerificationMode::Sms)) = vrm
+        .builder()
+        .login("john@example.com", "password", None, false)
+        .await
+    else {
+        panic!("expected a verification code to be required");
+    };
+
+    let LoginOutcome::VerificationRequired(pending) = vrm
+        .builder()
+        .begin_login("john@example.com", "password", false)
+        .await
+        .unwrap()
+    else {
+        panic!("expected a verification code to be required");
+    };
+    assert_eq!(pending.mode(), &VerificationMode::Sms);
+    assert!(pending.code_sent());
+
+    assert!(matches!(
+        pending.verify("000000").await,
+        Err(Error::InvalidVerificationCode)
+    ));
+
+    let victron = pending.verify("123456").await.unwrap();
+    victron.get_user_info().await.unwrap();
+
+    let body = vrm.requests()[3].body.clone().unwrap();
+    assert_eq!(body["sms_token"], "123456");
+}
+
+#[tokio::test]
+async fn login_without_verification() {
+    let vrm = MockVrm::new();
+
+    let outcome = vrm
+        .builder()
+        .begin_login("john@example.com", "password", false)
+        .await
+        .unwrap();
+
+    assert!(matches!(outcome, LoginOutcome::LoggedIn(_)));
+}
+
+#[tokio::test]
+async fn rate_limited_responses_are_failures() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    vrm.fail_next(MockFailure::RateLimited);
+
+    let Err(Error::Victron(failure)) = victron.get_user_info().await else {
+        panic!("expected a failure");
+    };
+    assert_eq!(failure.status, 429);
+    assert_eq!(failure.code, ErrorCode::RateLimited);
+    assert!(failure.body.is_none());
+}
+
+#[tokio::test]
+async fn html_error_pages_keep_their_body() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    vrm.fail_next(MockFailure::ServerError);
+
+    let Err(Error::Victron(Failure {
+        status,
+        code,
+        errors,
+        body,
+    })) = victron.get_user_info().await
+    else {
+        panic!("expected a failure");
+    };
+    assert_eq!(status, 500);
+    assert_eq!(
+        code,
+        ErrorCode::Unknown("500 Internal Server Error".to_string())
+    );
+    assert!(errors.is_null());
+    assert!(body.unwrap().contains("<html>"));
+}
+
+#[tokio::test]
+async fn error_codes_are_read_from_the_body() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    vrm.fail_next(MockFailure::Response {
+        status: 403,
+        body: r#"{"success": false, "errors": "Nope", "error_code": "forbidden"}"#.to_string(),
+    });
+
+    let Err(Error::Victron(failure)) = victron.get_user_info().await else {
+        panic!("expected a failure");
+    };
+    assert_eq!(failure.status, 403);
+    assert_eq!(failure.code, ErrorCode::Forbidden);
+    assert_eq!(failure.errors, "Nope");
+}
+
+#[tokio::test]
+async fn malformed_json_is_a_json_error() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    vrm.fail_next(MockFailure::MalformedJson);
+
+    assert!(matches!(victron.get_user_info().await, Err(Error::Json(_))));
+}
+
+#[tokio::test]
+async fn gets_one_installation() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+    let site_id = SiteId(151_734);
+
+    let installation = victron
+        .get_installation_or_site(true, site_id)
+        .await
+        .unwrap();
+    assert_eq!(installation.site_id, site_id);
+    assert!(installation.extended.is_some());
+
+    let request = vrm.requests().pop().unwrap();
+    assert_eq!(request.method, Method::GET);
+    assert_eq!(request.path, "/users/22/installations");
+    assert_eq!(request.query_param("idSite"), Some("151734"));
+    assert_eq!(request.query_param("extended"), Some("1"));
+
+    let installation = victron
+        .get_installation_or_site(false, site_id)
+        .await
+        .unwrap();
+    assert!(installation.extended.is_none());
+
+    let request = vrm.requests().pop().unwrap();
+    assert_eq!(request.query_param("extended"), Some("0"));
+}
+
+#[tokio::test]
+async fn pages_through_diagnostics() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+    let site_id = SiteId(151_734);
+
+    vrm.clear_requests();
+    let diagnostics = victron.get_diagnostics(site_id).await.unwrap();
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(vrm.requests().len(), 1);
+
+    // Script pages of an installation with more records than VRM returns at once.
+    let record = serde_json::to_value(&diagnostics[0]).unwrap();
+    let page = |count: usize| MockFailure::Response {
+        status: 200,
+        body: json!({ "success": true, "records": vec![&record; count], "num_records": 1500 })
+            .to_string(),
+    };
+    vrm.fail_next_on("/diagnostics", page(1000));
+    vrm.fail_next_on("/diagnostics", page(500));
+    vrm.clear_requests();
+
+    assert_eq!(victron.get_diagnostics(site_id).await.unwrap().len(), 1500);
+
+    let pages: Vec<_> = vrm
+        .requests()
+        .iter()
+        .map(|request| {
+            (
+                request.query_param("count").unwrap().to_string(),
+                request.query_param("offset").unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        pages,
+        [("1000".into(), "0".into()), ("1000".into(), "1000".into())]
+    );
+}
+
+#[tokio::test]
+async fn missing_installation_is_not_found() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+
+    assert!(matches!(
+        victron.get_installation_or_site(false, SiteId(1)).await,
+        Err(Error::InstallationNotFound(SiteId(1)))
+    ));
+}
+
+// Without the `tokio` feature, the rate limiter doesn't use tokio's clock, which is paused here.
+#[cfg(feature = "tokio")]
+#[tokio::test(start_paused = true)]
+async fn clones_share_the_rate_limit() {
+    use victron_energy_api::rate_limit::RateLimit;
+
+    let vrm = MockVrm::new();
+    let victron = vrm
+        .login()
+        .await
+        .unwrap()
+        .with_rate_limit(RateLimit::new(2, 1.0));
+    let clone = victron.clone();
+    let start = tokio::time::Instant::now();
+
+    victron.get_user_info().await.unwrap();
+    clone.get_user_info().await.unwrap();
+    assert_eq!(start.elapsed(), Duration::ZERO);
+
+    clone.get_user_info().await.unwrap();
+    assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+    // A client made with the builder has a bucket of its own.
+    let other = vrm
+        .login()
+        .await
+        .unwrap()
+        .with_rate_limit(RateLimit::new(2, 1.0));
+    other.get_user_info().await.unwrap();
+    assert_eq!(start.elapsed(), Duration::from_secs(1));
+}
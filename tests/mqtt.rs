@@ -0,0 +1,526 @@
+//! Runs the MQTT client against a scripted broker on the loopback interface, which acts like a GX device.
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+};
+use tokio_rustls::TlsAcceptor;
+use victron_energy_api::{
+    credentials::Credentials,
+    ids::SiteId,
+    mock::MockVrm,
+    mqtt::{MqttClient, MqttOptions, ValueKey, WriteOutcome},
+    Error, Victron,
+};
+#[cfg(feature = "rustls")]
+use victron_energy_api::{
+    mock,
+    tls::{Certificate, TlsConfig},
+    VictronBuilder,
+};
+
+/// The portal id of the first installation of the mock VRM.
+const PORTAL_ID: &str = "c0619ab1e2f3";
+
+/// What the broker sends after a client published a message.
+enum Reply {
+    /// Publishes a value of the GX device, as `N/<portal id>/<key>`.
+    Value(ValueKey, Value),
+    /// Waits before the next reply.
+    Wait(Duration),
+    /// Drops the connection.
+    Disconnect,
+}
+
+/// Accepts clients one after another and answers their packets, like the broker of a GX device would.
+///
+/// `login` is called with the password of every client that connects, and the login is rejected unless it returns
+/// true. The broker announces [`PORTAL_ID`] when asked for it, if `announce` is set. `script` is called with the
+/// topic and payload of every message a client publishes.
+async fn broker<L, S>(announce: bool, login: L, script: S) -> SocketAddr
+where
+    L: FnMut(Option<&str>) -> bool + Send + 'static,
+    S: FnMut(&str, &Value) -> Vec<Reply> + Send + 'static,
+{
+    listen(None, announce, login, script).await
+}
+
+/// Like [`broker`], but only accepts TLS connections, with a certificate for `127.0.0.1` issued by `certs/ca.pem`.
+async fn tls_broker<L, S>(announce: bool, login: L, script: S) -> SocketAddr
+where
+    L: FnMut(Option<&str>) -> bool + Send + 'static,
+    S: FnMut(&str, &Value) -> Vec<Reply> + Send + 'static,
+{
+    let certificates = rustls_pemfile::certs(&mut &include_bytes!("certs/broker.pem")[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    let key = rustls_pemfile::private_key(&mut &include_bytes!("certs/broker.key")[..])
+        .unwrap()
+        .unwrap();
+    let config = tokio_rustls::rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .unwrap();
+
+    listen(
+        Some(TlsAcceptor::from(Arc::new(config))),
+        announce,
+        login,
+        script,
+    )
+    .await
+}
+
+async fn listen<L, S>(
+    tls: Option<TlsAcceptor>,
+    announce: bool,
+    mut login: L,
+    mut script: S,
+) -> SocketAddr
+where
+    L: FnMut(Option<&str>) -> bool + Send + 'static,
+    S: FnMut(&str, &Value) -> Vec<Reply> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            match &tls {
+                Some(tls) => {
+                    // Clients that don't trust the certificate give up during the handshake.
+                    if let Ok(stream) = tls.accept(stream).await {
+                        serve(stream, announce, &mut login, &mut script).await;
+                    }
+                }
+                None => serve(stream, announce, &mut login, &mut script).await,
+            }
+        }
+    });
+
+    address
+}
+
+async fn serve<T, L, S>(mut stream: T, announce: bool, login: &mut L, script: &mut S)
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    L: FnMut(Option<&str>) -> bool,
+    S: FnMut(&str, &Value) -> Vec<Reply>,
+{
+    while let Some((header, body)) = read_packet(&mut stream).await {
+        match header >> 4 {
+            // CONNECT
+            1 => {
+                if login(connect_password(&body).as_deref()) {
+                    stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+                } else {
+                    // Bad user name or password.
+                    stream.write_all(&[0x20, 0x02, 0x00, 0x04]).await.unwrap();
+                    return;
+                }
+            }
+            // PUBLISH
+            3 => {
+                let topic_length = usize::from(u16::from_be_bytes([body[0], body[1]]));
+                let topic = std::str::from_utf8(&body[2..2 + topic_length]).unwrap();
+                let mut payload = &body[2 + topic_length..];
+
+                if header & 0x06 != 0 {
+                    let packet_id = &payload[..2];
+                    stream
+                        .write_all(&[0x40, 0x02, packet_id[0], packet_id[1]])
+                        .await
+                        .unwrap();
+                    payload = &payload[2..];
+                }
+
+                let payload = serde_json::from_slice(payload).unwrap_or_default();
+                for reply in script(topic, &payload) {
+                    match reply {
+                        Reply::Value(key, value) => {
+                            let topic = format!("N/{PORTAL_ID}/{key}");
+                            publish(&mut stream, &topic, &json!({ "value": value })).await;
+                        }
+                        Reply::Wait(duration) => tokio::time::sleep(duration).await,
+                        Reply::Disconnect => return,
+                    }
+                }
+            }
+            // SUBSCRIBE
+            8 => {
+                stream
+                    .write_all(&[0x90, 0x03, body[0], body[1], 0x00])
+                    .await
+                    .unwrap();
+
+                if announce && body.ends_with(b"N/+/system/0/Serial\x00") {
+                    let topic = format!("N/{PORTAL_ID}/system/0/Serial");
+                    publish(&mut stream, &topic, &json!({ "value": PORTAL_ID })).await;
+                }
+            }
+            // UNSUBSCRIBE
+            10 => stream
+                .write_all(&[0xb0, 0x02, body[0], body[1]])
+                .await
+                .unwrap(),
+            // PINGREQ
+            12 => stream.write_all(&[0xd0, 0x00]).await.unwrap(),
+            // DISCONNECT
+            14 => return,
+            _ => {}
+        }
+    }
+}
+
+/// Reads the first byte of the fixed header and the rest of a packet, or `None` once the client is gone.
+async fn read_packet(stream: &mut (impl AsyncRead + Unpin)) -> Option<(u8, Vec<u8>)> {
+    let header = stream.read_u8().await.ok()?;
+
+    let mut length = 0;
+    for shift in (0..28).step_by(7) {
+        let byte = stream.read_u8().await.ok()?;
+        length |= usize::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.ok()?;
+
+    Some((header, body))
+}
+
+/// Reads the password from the body of a CONNECT packet.
+fn connect_password(body: &[u8]) -> Option<String> {
+    // The protocol name, level, flags and keepalive come first.
+    let flags = body[7];
+    let mut payload = &body[10..];
+
+    let mut fields = std::iter::from_fn(|| {
+        let length = usize::from(u16::from_be_bytes([payload[0], payload[1]]));
+        let field = payload[2..2 + length].to_vec();
+        payload = &payload[2 + length..];
+        Some(field)
+    });
+
+    let _client_id = fields.next();
+    if flags & 0x04 != 0 {
+        let _will_topic = fields.next();
+        let _will_message = fields.next();
+    }
+    if flags & 0x80 != 0 {
+        let _username = fields.next();
+    }
+    if flags & 0x40 == 0 {
+        return None;
+    }
+
+    Some(String::from_utf8(fields.next()?).unwrap())
+}
+
+async fn publish(stream: &mut (impl AsyncWrite + Unpin), topic: &str, payload: &Value) {
+    let payload = serde_json::to_vec(payload).unwrap();
+    let topic_length = u16::try_from(topic.len()).unwrap();
+
+    let mut body = topic_length.to_be_bytes().to_vec();
+    body.extend_from_slice(topic.as_bytes());
+    body.extend_from_slice(&payload);
+
+    let mut packet = vec![0x30];
+    let mut length = body.len();
+    loop {
+        let byte = u8::try_from(length & 0x7f).unwrap();
+        length >>= 7;
+        if length == 0 {
+            packet.push(byte);
+            break;
+        }
+        packet.push(byte | 0x80);
+    }
+    packet.extend_from_slice(&body);
+
+    stream.write_all(&packet).await.unwrap();
+}
+
+/// A GX device with an ESS minimum state of charge of 10%, which answers reads of it with `answer`.
+fn ess_device(
+    mut answer: impl FnMut(&Value) -> Vec<Reply> + Send + 'static,
+) -> impl FnMut(&str, &Value) -> Vec<Reply> + Send + 'static {
+    let key = ValueKey::ess_minimum_soc();
+    let mut written = Value::Null;
+
+    move |topic, payload| {
+        if topic == format!("R/{PORTAL_ID}/keepalive") {
+            vec![Reply::Value(key.clone(), json!(10))]
+        } else if topic == format!("W/{PORTAL_ID}/{key}") {
+            written = payload["value"].clone();
+            Vec::new()
+        } else if topic == format!("R/{PORTAL_ID}/{key}") {
+            answer(&written)
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+async fn connect(address: SocketAddr) -> MqttClient {
+    let options = MqttOptions::local()
+        .port(address.port())
+        .local_writes(true)
+        .write_timeout(Duration::from_millis(500));
+
+    let mqtt = MqttClient::connect_local(&address.ip().to_string(), options)
+        .await
+        .unwrap();
+
+    // Wait for the values the device publishes after connecting.
+    let mut updates = Box::pin(mqtt.updates());
+    while mqtt.value(&ValueKey::ess_minimum_soc()).is_none() {
+        tokio::time::timeout(Duration::from_secs(5), updates.next())
+            .await
+            .unwrap();
+    }
+
+    mqtt
+}
+
+#[tokio::test]
+async fn connect_local_discovers_the_portal_id() {
+    let address = broker(true, |_| true, ess_device(|_| Vec::new())).await;
+    let options = MqttOptions::local().port(address.port());
+
+    let mqtt = MqttClient::connect_local(&address.ip().to_string(), options)
+        .await
+        .unwrap();
+    assert_eq!(mqtt.portal_id(), PORTAL_ID);
+
+    // Writes to a local broker have to be enabled.
+    assert!(matches!(
+        mqtt.set_ess_minimum_soc(20.0).await,
+        Err(Error::MqttWriteNotPermitted)
+    ));
+}
+
+#[tokio::test]
+async fn discovery_times_out() {
+    let address = broker(false, |_| true, ess_device(|_| Vec::new())).await;
+    let options = MqttOptions::local()
+        .port(address.port())
+        .write_timeout(Duration::from_secs(60))
+        .discovery_timeout(Duration::from_millis(200));
+
+    let connection = tokio::time::timeout(
+        Duration::from_secs(5),
+        MqttClient::connect_local(&address.ip().to_string(), options),
+    )
+    .await
+    .expect("discovery should use its own timeout");
+    assert!(matches!(connection, Err(Error::MqttDiscoveryTimeout)));
+}
+
+#[tokio::test]
+async fn write_waits_for_the_written_value() {
+    let address = broker(
+        true,
+        |_| true,
+        ess_device(|written| {
+            assert_eq!(written, &json!(20.0));
+
+            vec![
+                // The cached value, from before the change took effect.
+                Reply::Value(ValueKey::ess_minimum_soc(), json!(10)),
+                Reply::Wait(Duration::from_millis(100)),
+                // The device sends whole numbers without a fraction.
+                Reply::Value(ValueKey::ess_minimum_soc(), json!(20)),
+            ]
+        }),
+    )
+    .await;
+    let mqtt = connect(address).await;
+
+    let outcome = mqtt.set_ess_minimum_soc(20.0).await.unwrap();
+
+    assert!(outcome.is_confirmed());
+    assert_eq!(outcome.update().raw_value, json!(20));
+}
+
+#[tokio::test]
+async fn write_can_be_rejected() {
+    let address = broker(
+        true,
+        |_| true,
+        ess_device(|_| vec![Reply::Value(ValueKey::ess_minimum_soc(), json!(10))]),
+    )
+    .await;
+    let mqtt = connect(address).await;
+
+    let Ok(WriteOutcome::Rejected(update)) = mqtt.set_ess_minimum_soc(120.0).await else {
+        panic!("expected the write to be rejected");
+    };
+    assert_eq!(update.raw_value, json!(10));
+}
+
+#[tokio::test]
+async fn write_can_be_adjusted() {
+    let address = broker(
+        true,
+        |_| true,
+        ess_device(|_| vec![Reply::Value(ValueKey::ess_minimum_soc(), json!(25))]),
+    )
+    .await;
+    let mqtt = connect(address).await;
+
+    let Ok(WriteOutcome::Adjusted(update)) = mqtt.set_ess_minimum_soc(23.0).await else {
+        panic!("expected the write to be adjusted");
+    };
+    assert_eq!(update.raw_value, json!(25));
+}
+
+#[tokio::test]
+async fn write_times_out_without_an_answer() {
+    let address = broker(true, |_| true, ess_device(|_| Vec::new())).await;
+    let mqtt = connect(address).await;
+
+    assert!(matches!(
+        mqtt.set_ess_minimum_soc(20.0).await,
+        Err(Error::MqttWriteTimeout(key)) if key == ValueKey::ess_minimum_soc()
+    ));
+}
+
+/// A GX device that drops the first connection after publishing the ESS minimum state of charge, and publishes it
+/// again with another value after every later connection.
+fn flaky_device() -> impl FnMut(&str, &Value) -> Vec<Reply> + Send + 'static {
+    let mut connections = 0;
+
+    move |topic, _| {
+        if topic != format!("R/{PORTAL_ID}/keepalive") {
+            return Vec::new();
+        }
+
+        connections += 1;
+        if connections == 1 {
+            vec![
+                Reply::Value(ValueKey::ess_minimum_soc(), json!(10)),
+                Reply::Disconnect,
+            ]
+        } else {
+            vec![Reply::Value(ValueKey::ess_minimum_soc(), json!(20))]
+        }
+    }
+}
+
+/// Accepts the first token only on the first connection, as if it expired when the connection dropped.
+fn expiring_login() -> impl FnMut(Option<&str>) -> bool + Send + 'static {
+    let mut connections = 0;
+
+    move |password| {
+        connections += 1;
+        let token = if connections == 1 { 1 } else { 2 };
+        password == Some(format!("Bearer mock-token-{token}").as_str())
+    }
+}
+
+async fn connect_vrm(victron: &Victron<MockVrm>, address: SocketAddr) -> MqttClient {
+    let installation = victron
+        .get_installation_or_site(false, SiteId(151_734))
+        .await
+        .unwrap();
+    let options = MqttOptions::new()
+        .host(&address.ip().to_string())
+        .port(address.port())
+        .plaintext();
+
+    victron.connect_mqtt(&installation, options).await.unwrap()
+}
+
+#[tokio::test]
+async fn reconnects_with_a_fresh_token() {
+    let vrm = MockVrm::new();
+    let victron = vrm
+        .login()
+        .await
+        .unwrap()
+        .with_credentials(Credentials::Password {
+            username: "john@example.com".to_string(),
+            password: "password".to_string(),
+        });
+    let address = broker(false, expiring_login(), flaky_device()).await;
+
+    let mqtt = connect_vrm(&victron, address).await;
+    let mut updates = Box::pin(mqtt.updates());
+
+    for value in [10, 20] {
+        let update = tokio::time::timeout(Duration::from_secs(15), updates.next())
+            .await
+            .expect("the client should reconnect")
+            .unwrap();
+        assert_eq!(update.raw_value, json!(value));
+    }
+    assert_eq!(victron.session().await.token, "mock-token-2");
+}
+
+#[tokio::test]
+async fn rejected_login_without_credentials_ends_the_updates() {
+    let vrm = MockVrm::new();
+    let victron = vrm.login().await.unwrap();
+    let address = broker(false, expiring_login(), flaky_device()).await;
+
+    let mqtt = connect_vrm(&victron, address).await;
+    let mut updates = Box::pin(mqtt.updates());
+
+    let update = updates.next().await.unwrap();
+    assert_eq!(update.raw_value, json!(10));
+
+    let end = tokio::time::timeout(Duration::from_secs(15), updates.next())
+        .await
+        .expect("the updates should end");
+    assert!(end.is_none());
+}
+
+#[cfg(feature = "rustls")]
+#[tokio::test]
+async fn uses_the_tls_options_of_the_client() {
+    let vrm = MockVrm::new();
+    let tls = TlsConfig::new()
+        .built_in_roots(false)
+        .add_root_certificate(Certificate::from_pem(include_bytes!("certs/ca.pem")));
+    let victron = VictronBuilder::new()
+        .tls(&tls)
+        .unwrap()
+        .transport(vrm.clone())
+        .base_url(mock::BASE_URL)
+        .login("john@example.com", "password", None, false)
+        .await
+        .unwrap();
+    let installation = victron
+        .get_installation_or_site(false, SiteId(151_734))
+        .await
+        .unwrap();
+    let address = tls_broker(false, |_| true, ess_device(|_| Vec::new())).await;
+    let options = || {
+        MqttOptions::new()
+            .host(&address.ip().to_string())
+            .port(address.port())
+    };
+
+    let mqtt = victron
+        .connect_mqtt(&installation, options())
+        .await
+        .unwrap();
+    assert_eq!(mqtt.portal_id(), PORTAL_ID);
+    // The broker serves one client at a time.
+    drop(mqtt);
+
+    // Options of the connection itself take precedence.
+    assert!(matches!(
+        victron
+            .connect_mqtt(&installation, options().tls(TlsConfig::new()))
+            .await,
+        Err(Error::MqttConnection(_))
+    ));
+}